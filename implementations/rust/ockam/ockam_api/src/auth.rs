@@ -1,14 +1,21 @@
 pub mod types;
 
 use core::fmt;
-use minicbor::Decoder;
-use ockam_core::api::{decode_option, is_ok};
-use ockam_core::api::{Method, Request, Response};
+use core::time::Duration;
+use minicbor::{Decoder, Encode};
+use ockam_core::api::{check_version, decode_option, is_ok};
+use ockam_core::api::{Method, Request, RequestBuilder, Response};
+use ockam_core::compat::boxed::Box;
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{self, Address, Result, Route, Routed, Worker};
 use ockam_identity::authenticated_storage::AuthenticatedStorage;
-use ockam_node::api::request;
-use ockam_node::Context;
-use tracing::trace;
+use ockam_identity::credential::Attributes;
+use ockam_node::api::{request_with_timeout, retry};
+use ockam_node::tokio::sync::Mutex;
+use ockam_node::{Context, DEFAULT_TIMEOUT};
+use ockam_transport_core::{ExponentialReconnect, ReconnectStrategy};
+use tracing::{debug, trace};
 use types::Attribute;
 
 /// Auth API server.
@@ -50,6 +57,10 @@ impl<S: AuthenticatedStorage> Server<S> {
             "request"
         }
 
+        if let Some(res) = check_version(&req) {
+            return Ok(res.to_vec()?);
+        }
+
         let res = match req.method() {
             Some(Method::Get) => match req.path_segments::<5>().as_slice() {
                 ["authenticated", id, "attribute", key] => {
@@ -59,12 +70,34 @@ impl<S: AuthenticatedStorage> Server<S> {
                         Response::not_found(req.id()).to_vec()?
                     }
                 }
+                ["authenticated", id, "attributes"] => {
+                    let entries = self.store.list(id).await?;
+                    let mut attrs = Attributes::new();
+                    for (k, v) in &entries {
+                        attrs.put(k, v);
+                    }
+                    Response::ok(req.id()).body(attrs).to_vec()?
+                }
+                _ => ockam_core::api::unknown_path(&req).to_vec()?,
+            },
+            Some(Method::Put) => match req.path_segments::<5>().as_slice() {
+                ["authenticated", id, "attribute", key] => {
+                    if !req.has_body() {
+                        Response::bad_request(req.id()).to_vec()?
+                    } else {
+                        let a: Attribute = dec.decode()?;
+                        self.store
+                            .set(id, key.to_string(), a.value().to_vec())
+                            .await?;
+                        Response::ok(req.id()).to_vec()?
+                    }
+                }
                 _ => ockam_core::api::unknown_path(&req).to_vec()?,
             },
             Some(Method::Delete) => match req.path_segments::<5>().as_slice() {
                 ["authenticated", id, "attribute", key] => {
                     self.store.del(id, key).await?;
-                    Response::ok(req.id()).to_vec()?
+                    Response::no_content(req.id()).to_vec()?
                 }
                 _ => ockam_core::api::unknown_path(&req).to_vec()?,
             },
@@ -80,38 +113,151 @@ pub struct Client {
     ctx: Context,
     route: Route,
     buf: Vec<u8>,
+    timeout: Duration,
+    retry: Box<dyn ReconnectStrategy>,
 }
 
 impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Client")
             .field("route", &self.route)
+            .field("timeout", &self.timeout)
+            .field("retry", &self.retry)
             .finish()
     }
 }
 
+/// How often a `Client` request is retried by default before a transient failure is returned.
+const REQUEST_RETRIES: u32 = 2;
+
+/// Delay before the first retry of a `Client` request by default, doubled for every further one.
+const REQUEST_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest delay between two retries of a `Client` request by default.
+const REQUEST_RETRY_MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// How often `Client::new` picks another random address if the previous one was taken.
+const DETACHED_CONTEXT_ATTEMPTS: usize = 3;
+
 impl Client {
     pub async fn new(r: Route, ctx: &Context) -> ockam_core::Result<Self> {
-        let ctx = ctx.new_detached(Address::random_local()).await?;
+        let ctx = Self::detached_context(ctx).await?;
         Ok(Client {
             ctx,
             route: r,
             buf: Vec::new(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            retry: Box::new(ExponentialReconnect::new(
+                REQUEST_RETRY_DELAY,
+                REQUEST_RETRY_MAX_DELAY,
+                REQUEST_RETRIES,
+            )),
         })
     }
 
+    /// Retry a request that failed transiently as long as `strategy` allows.
+    ///
+    /// With [`NoReconnect`](ockam_transport_core::NoReconnect) a request is never retried.
+    pub fn with_retry_strategy(mut self, strategy: impl ReconnectStrategy) -> Self {
+        self.retry = Box::new(strategy);
+        self
+    }
+
+    /// Give up on an attempt of a request after `timeout` without response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Create the client's own context at a fresh random address.
+    ///
+    /// A collision with an existing address is retried with a new one,
+    /// any other failure is reported together with the address chosen.
+    async fn detached_context(ctx: &Context) -> ockam_core::Result<Context> {
+        let mut attempt = 1;
+        loop {
+            let addr = Address::random_local();
+            match ctx.new_detached(addr.clone()).await {
+                Ok(ctx) => return Ok(ctx),
+                Err(e)
+                    if e.code().kind == Kind::AlreadyExists
+                        && attempt < DETACHED_CONTEXT_ATTEMPTS =>
+                {
+                    debug!(%addr, "client address already in use, picking another one");
+                    attempt += 1
+                }
+                Err(e) => {
+                    let msg =
+                        format!("failed to create detached context for client at {addr}: {e}");
+                    return Err(ockam_core::Error::new(
+                        Origin::Application,
+                        e.code().kind,
+                        msg,
+                    ));
+                }
+            }
+        }
+    }
+
     pub async fn get(&mut self, id: &str, attr: &str) -> ockam_core::Result<Option<&[u8]>> {
         let label = "get attribute";
-        let req = Request::get(format!("/authenticated/{id}/attribute/{attr}"));
-        self.buf = request(&mut self.ctx, label, None, self.route.clone(), req).await?;
+        let path = format!("/authenticated/{id}/attribute/{attr}");
+        self.buf = self
+            .request(label, None, || Request::get(path.as_str()))
+            .await?;
         let a: Option<Attribute> = decode_option(label, "attribute", &self.buf)?;
         Ok(a.map(|a| a.value()))
     }
 
+    /// Get all attributes of a subject, which may be none.
+    pub async fn list(&mut self, id: &str) -> ockam_core::Result<BTreeMap<String, Vec<u8>>> {
+        let label = "list attributes";
+        let path = format!("/authenticated/{id}/attributes");
+        self.buf = self
+            .request(label, None, || Request::get(path.as_str()))
+            .await?;
+        let a: Option<Attributes> = decode_option(label, "attributes", &self.buf)?;
+        Ok(a.map(|a| a.to_owned()).unwrap_or_default())
+    }
+
+    pub async fn set(&mut self, id: &str, attr: &str, val: &[u8]) -> ockam_core::Result<()> {
+        let label = "set attribute";
+        let path = format!("/authenticated/{id}/attribute/{attr}");
+        let req = || Request::put(path.as_str()).body(Attribute::new(val));
+        self.buf = self.request(label, "attribute", req).await?;
+        is_ok(label, &self.buf)
+    }
+
     pub async fn del(&mut self, id: &str, attr: &str) -> ockam_core::Result<()> {
         let label = "del attribute";
-        let req = Request::delete(format!("/authenticated/{id}/attribute/{attr}"));
-        self.buf = request(&mut self.ctx, label, None, self.route.clone(), req).await?;
+        let path = format!("/authenticated/{id}/attribute/{attr}");
+        self.buf = self
+            .request(label, None, || Request::delete(path.as_str()))
+            .await?;
         is_ok(label, &self.buf)
     }
+
+    /// Send a request and return the response, retrying on transient failures.
+    ///
+    /// All attempts share a fresh context of their own, a late response to
+    /// a previous attempt is told apart by its request id and dropped.
+    async fn request<'r, T, F>(
+        &self,
+        label: &str,
+        struct_name: impl Into<Option<&str>> + Copy,
+        req: F,
+    ) -> ockam_core::Result<Vec<u8>>
+    where
+        T: Encode<()>,
+        F: Fn() -> RequestBuilder<'r, T>,
+    {
+        // Every attempt borrows the context mutably, one after the other.
+        let ctx = Mutex::new(Self::detached_context(&self.ctx).await?);
+        retry(label, &*self.retry, || async {
+            let mut ctx = ctx.lock().await;
+            let route = self.route.clone();
+            request_with_timeout(&mut ctx, label, struct_name, route, req(), self.timeout).await
+        })
+        .await
+    }
 }
@@ -97,6 +97,30 @@ impl AuthenticatedStorage for LmdbStorage {
     async fn del(&self, id: &str, key: &str) -> Result<()> {
         self.delete(format!("{id}:{key}")).await
     }
+
+    /// Entries are stored under `{id}:{key}`, so a key that still
+    /// contains a `:` belongs to another id starting with `{id}:`, and
+    /// is skipped.
+    async fn list(&self, id: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let d = self.clone();
+        let prefix = format!("{id}:");
+        let t = move || {
+            let tx = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
+            let mut c = tx.open_ro_cursor(d.map).map_err(map_lmdb_err)?;
+            let mut xs = Vec::new();
+            for entry in c.iter_from(prefix.as_str()) {
+                let (k, v) = entry.map_err(map_lmdb_err)?;
+                let ks = str::from_utf8(k).map_err(from_utf8_err)?;
+                match ks.strip_prefix(prefix.as_str()) {
+                    Some(key) if key.contains(':') => continue,
+                    Some(key) => xs.push((key.to_string(), v.to_vec())),
+                    None => break,
+                }
+            }
+            Ok(xs)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)?
+    }
 }
 
 /// Policy storage entry.
@@ -4,7 +4,7 @@ use minicbor::Decoder;
 
 use ockam::compat::asynchronous::RwLock;
 use ockam::{Address, Context, ForwardingService, Result, Routed, TcpTransport, Worker};
-use ockam_core::api::{Error, Method, Request, Response, ResponseBuilder, Status};
+use ockam_core::api::{check_version, Error, Method, Request, Response, ResponseBuilder, Status};
 use ockam_core::compat::{
     boxed::Box,
     string::String,
@@ -761,6 +761,11 @@ impl Worker for NodeManagerWorker {
             }
         };
 
+        if let Some(res) = check_version(&req) {
+            warn!(target: TARGET, version = ?req.version(), "unsupported protocol version");
+            return ctx.send(msg.return_route(), res.to_vec()?).await;
+        }
+
         let r = match self.handle_request(ctx, &req, &mut dec).await {
             Ok(r) => r,
             Err(err) => {
@@ -1,6 +1,12 @@
+use core::time::Duration;
 use ockam::authenticated_storage::{AuthenticatedStorage, InMemoryStorage};
 use ockam_api::auth;
-use ockam_core::Result;
+use ockam_api::auth::types::Attribute;
+use ockam_api::lmdb::LmdbStorage;
+use ockam_core::api::{Request, Response, Status, PROTOCOL_VERSION};
+use ockam_core::{route, Any, Result, Routed, Worker};
+use ockam_node::api::request;
+use ockam_node::tokio::time::sleep;
 use ockam_node::Context;
 
 #[ockam_macros::test]
@@ -20,5 +26,152 @@ async fn auth_smoke(ctx: &mut Context) -> Result<()> {
     client.del("foo", "a").await?;
     assert_eq!(None, client.get("foo", "a").await?);
 
+    let attrs = client.list("foo").await?;
+    assert_eq!(1, attrs.len());
+    assert_eq!(Some(&b"world".to_vec()), attrs.get("b"));
+    assert!(client.list("nobody").await?.is_empty());
+
+    client.set("bar", "c", b"again").await?;
+    assert_eq!(Some(&b"again"[..]), client.get("bar", "c").await?);
+    assert_eq!(Some(b"again".to_vec()), s.get("bar", "c").await?);
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn auth_delete_has_no_content(ctx: &mut Context) -> Result<()> {
+    let s = InMemoryStorage::new();
+    ctx.start_worker("auth", auth::Server::new(s.clone()))
+        .await?;
+    s.set("foo", "a".to_string(), b"hello".to_vec()).await?;
+
+    let req = Request::delete("/authenticated/foo/attribute/a");
+    let buf = request(ctx, "del attribute", None, "auth", req).await?;
+    let res: Response = minicbor::decode(&buf).unwrap();
+    assert_eq!(Some(Status::NoContent), res.status());
+    assert!(!res.has_body());
+    assert_eq!(None, s.get("foo", "a").await?);
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn auth_rejects_newer_protocol_version(ctx: &mut Context) -> Result<()> {
+    let s = InMemoryStorage::new();
+    ctx.start_worker("auth", auth::Server::new(s.clone()))
+        .await?;
+    s.set("foo", "a".to_string(), b"hello".to_vec()).await?;
+
+    let req = Request::get("/authenticated/foo/attribute/a").version(PROTOCOL_VERSION + 1);
+    let buf = request(ctx, "get attribute", None, "auth", req).await?;
+    let res: Response = minicbor::decode(&buf).unwrap();
+    assert_eq!(Some(Status::VersionNotSupported), res.status());
+
+    ctx.stop().await
+}
+
+/// Forwards messages to the next hop, except for the first one, which is lost.
+struct FlakyHop {
+    dropped: bool,
+}
+
+#[ockam_core::worker]
+impl Worker for FlakyHop {
+    type Context = Context;
+    type Message = Any;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        if !self.dropped {
+            self.dropped = true;
+            return Ok(());
+        }
+        let mut msg = msg.into_local_message();
+        msg.transport_mut().onward_route.step()?;
+        ctx.forward(msg).await
+    }
+}
+
+#[ockam_macros::test]
+async fn auth_client_retries_lost_request(ctx: &mut Context) -> Result<()> {
+    let s = InMemoryStorage::new();
+    ctx.start_worker("auth", auth::Server::new(s.clone()))
+        .await?;
+    ctx.start_worker("flaky", FlakyHop { dropped: false })
+        .await?;
+    s.set("foo", "a".to_string(), b"hello".to_vec()).await?;
+
+    let mut client = auth::Client::new(route!["flaky", "auth"], ctx)
+        .await?
+        .with_timeout(Duration::from_millis(500));
+
+    // The first attempt times out, the second one goes through
+    assert_eq!(Some(&b"hello"[..]), client.get("foo", "a").await?);
+
+    ctx.stop().await
+}
+
+/// Answers every request with the attribute value `"fresh"`, except for
+/// the first one, which is answered with `"stale"` after `delay`.
+struct SlowServer {
+    delay: Duration,
+    answered: bool,
+}
+
+#[ockam_core::worker]
+impl Worker for SlowServer {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Vec<u8>>) -> Result<()> {
+        let req: Request = minicbor::decode(msg.as_body()).unwrap();
+        let val: &[u8] = if self.answered {
+            b"fresh"
+        } else {
+            self.answered = true;
+            sleep(self.delay).await;
+            b"stale"
+        };
+        let res = Response::ok(req.id()).body(Attribute::new(val)).to_vec()?;
+        ctx.send(msg.return_route(), res).await
+    }
+}
+
+#[ockam_macros::test]
+async fn auth_client_drops_late_response(ctx: &mut Context) -> Result<()> {
+    let timeout = Duration::from_millis(300);
+    let server = SlowServer {
+        delay: timeout * 2,
+        answered: false,
+    };
+    ctx.start_worker("slow", server).await?;
+
+    let mut client = auth::Client::new("slow".into(), ctx)
+        .await?
+        .with_timeout(timeout);
+
+    // The response to the first attempt arrives during the second one
+    assert_eq!(Some(&b"fresh"[..]), client.get("foo", "a").await?);
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn auth_list_keeps_subjects_apart(ctx: &mut Context) -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let s = LmdbStorage::new(dir.path().join("lmdb")).await?;
+    ctx.start_worker("auth", auth::Server::new(s.clone()))
+        .await?;
+
+    s.set("a", "x".to_string(), b"mine".to_vec()).await?;
+    s.set("a:x", "y".to_string(), b"theirs".to_vec()).await?;
+    s.set("a", "z".to_string(), b"also mine".to_vec()).await?;
+
+    let mut client = auth::Client::new("auth".into(), ctx).await?;
+    let attrs = client.list("a").await?;
+    assert_eq!(2, attrs.len());
+    assert_eq!(Some(&b"mine".to_vec()), attrs.get("x"));
+    assert_eq!(Some(&b"also mine".to_vec()), attrs.get("z"));
+    assert_eq!(1, client.list("a:x").await?.len());
+
     ctx.stop().await
 }
@@ -1,28 +1,66 @@
 use crate::help;
-use crate::util::embedded_node;
-use anyhow::{anyhow, Result};
+use crate::util::node_rpc;
+use crate::{exitcode, CommandGlobalOpts, Error, OutputFormat, Result};
+use anyhow::anyhow;
 use clap::builder::NonEmptyStringValueParser;
 use clap::{Args, Subcommand};
 use ockam::{Context, TcpTransport};
 use ockam_api::auth;
-use ockam_multiaddr::MultiAddr;
+use ockam_core::errcode::Kind;
+use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Secure, Service, Tcp};
+use ockam_multiaddr::{MultiAddr, ProtoValue, Protocol};
+use ockam_node::api::is_transient;
+use ockam_transport_core::ExponentialReconnect;
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use tokio::time::Duration;
 
 const HELP_DETAIL: &str = "";
 
+/// Delay before the first retry of a request, doubled for every further one.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest delay between two retries of a request.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Clone, Debug, Args)]
 #[command(hide = help::hide(), after_long_help = help::template(HELP_DETAIL))]
 pub struct AuthenticatedCommand {
     #[command(subcommand)]
     subcommand: AuthenticatedSubcommand,
+
+    /// Address to connect to, `OCKAM_ADDR` is used if omitted.
+    #[arg(global = true, long, env = "OCKAM_ADDR", value_name = "ADDRESS")]
+    addr: Option<String>,
+
+    /// Seconds to wait for the response to each attempt of a request.
+    #[arg(global = true, long, value_name = "SECONDS", default_value_t = 10)]
+    timeout: u64,
+
+    /// How many times to retry a request after an attempt failed to reach the address.
+    ///
+    /// Every request is retried on its own, e.g. one per id read from stdin.
+    #[arg(global = true, long, default_value_t = 0)]
+    retries: u32,
 }
 
 #[derive(Clone, Debug, Subcommand)]
 pub enum AuthenticatedSubcommand {
     /// Get attribute value.
     Get {
-        /// Address to connect to.
-        addr: MultiAddr,
+        /// Subject identifier, `-` to read one per line from stdin
+        #[arg(long, value_parser(NonEmptyStringValueParser::new()))]
+        id: String,
 
+        /// Attribute key.
+        #[arg(value_parser(NonEmptyStringValueParser::new()))]
+        key: String,
+    },
+    /// Set attribute value.
+    Set {
         /// Subject identifier
         #[arg(long, value_parser(NonEmptyStringValueParser::new()))]
         id: String,
@@ -30,12 +68,20 @@ pub enum AuthenticatedSubcommand {
         /// Attribute key.
         #[arg(value_parser(NonEmptyStringValueParser::new()))]
         key: String,
+
+        /// Attribute value.
+        #[arg(value_parser(NonEmptyStringValueParser::new()))]
+        value: String,
+    },
+    /// List all attributes of a subject.
+    List {
+        /// Subject identifier
+        #[arg(long, value_parser(NonEmptyStringValueParser::new()))]
+        id: String,
     },
     /// Delete attribute
+    #[command(visible_alias = "delete")]
     Del {
-        /// Address to connect to.
-        addr: MultiAddr,
-
         /// Subject identifier
         #[arg(long, value_parser(NonEmptyStringValueParser::new()))]
         id: String,
@@ -47,33 +93,256 @@ pub enum AuthenticatedSubcommand {
 }
 
 impl AuthenticatedCommand {
-    pub fn run(self) {
-        if let Err(e) = embedded_node(run_impl, self.subcommand) {
-            eprintln!("Ockam node failed: {:?}", e,);
-        }
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(run_impl, (opts, self))
     }
 }
 
-async fn run_impl(ctx: Context, cmd: AuthenticatedSubcommand) -> crate::Result<()> {
+impl AuthenticatedCommand {
+    /// The address given with `--addr`, or else in `OCKAM_ADDR`.
+    fn addr(&self) -> Result<&str> {
+        self.addr.as_deref().ok_or_else(|| {
+            Error::new(
+                exitcode::USAGE,
+                anyhow!("no address given, pass --addr or set OCKAM_ADDR"),
+            )
+        })
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, AuthenticatedCommand),
+) -> Result<()> {
+    let addr = cmd.addr()?;
     TcpTransport::create(&ctx).await?;
-    match &cmd {
-        AuthenticatedSubcommand::Get { addr, id, key } => {
-            let mut c = client(addr, &ctx).await?;
-            let val = c.get(id, key).await?;
-            println!("{val:?}")
+
+    let ids = match &cmd.subcommand {
+        AuthenticatedSubcommand::Get { id, .. } if id == "-" => read_ids(io::stdin().lock())?,
+        AuthenticatedSubcommand::Get { id, .. } => vec![id.clone()],
+        _ => Vec::new(),
+    };
+
+    let mut c = client(addr, &ctx, &cmd).await?;
+    let failed = |e: ockam::Error| {
+        if e.code().kind == Kind::Timeout {
+            Error::new(
+                exitcode::UNAVAILABLE,
+                anyhow!("could not reach {addr} within {}s", cmd.timeout),
+            )
+        } else if is_transient(&e) {
+            Error::new(
+                exitcode::UNAVAILABLE,
+                anyhow!("could not reach {addr}: {e}"),
+            )
+        } else {
+            e.into()
+        }
+    };
+    let format = &opts.global_args.output_format;
+    match &cmd.subcommand {
+        AuthenticatedSubcommand::Get { key, .. } => {
+            for id in &ids {
+                let val = c.get(id, key).await.map_err(failed)?;
+                println!("{}", format_value(val, format))
+            }
         }
-        AuthenticatedSubcommand::Del { addr, id, key } => {
-            let mut c = client(addr, &ctx).await?;
-            c.del(id, key).await?;
+        AuthenticatedSubcommand::List { id } => {
+            let attrs = c.list(id).await.map_err(failed)?;
+            println!("{}", format_list(&attrs, format))
+        }
+        AuthenticatedSubcommand::Set { id, key, value } => {
+            c.set(id, key, value.as_bytes()).await.map_err(failed)?;
+            println!("{}", format_change("Set", id, key, format))
+        }
+        AuthenticatedSubcommand::Del { id, key } => {
+            c.del(id, key).await.map_err(failed)?;
+            println!("{}", format_change("Deleted", id, key, format))
         }
     }
 
     Ok(())
 }
 
-async fn client(addr: &MultiAddr, ctx: &Context) -> Result<auth::Client> {
-    let to = ockam_api::multiaddr_to_route(addr)
-        .ok_or_else(|| anyhow!("failed to parse address: {addr}"))?;
-    let cl = auth::Client::new(to, ctx).await?;
+/// Create an `auth::Client`, an address that can't be routed to is a usage error.
+///
+/// The client retries each request itself, as often as `--retries` says.
+async fn client(addr: &str, ctx: &Context, cmd: &AuthenticatedCommand) -> Result<auth::Client> {
+    let ma = MultiAddr::from_str(addr).map_err(|e| {
+        Error::new(
+            exitcode::USAGE,
+            anyhow!("{addr} is not a valid MultiAddr: {e}"),
+        )
+    })?;
+    if let Some(reason) = unroutable_reason(&ma) {
+        return Err(Error::new(
+            exitcode::USAGE,
+            anyhow!("{addr} can't be routed to: {reason}"),
+        ));
+    }
+    let to = ockam_api::multiaddr_to_route(&ma)
+        .ok_or_else(|| Error::new(exitcode::USAGE, anyhow!("{addr} can't be routed to")))?;
+    let cl = auth::Client::new(to, ctx)
+        .await?
+        .with_timeout(Duration::from_secs(cmd.timeout))
+        .with_retry_strategy(ExponentialReconnect::new(
+            RETRY_DELAY,
+            RETRY_MAX_DELAY,
+            cmd.retries,
+        ));
     Ok(cl)
 }
+
+/// Explain which protocol of `ma` prevents it from being converted to a route.
+fn unroutable_reason(ma: &MultiAddr) -> Option<String> {
+    let mut it = ma.iter().peekable();
+    while let Some(p) = it.next() {
+        match p.code() {
+            Ip4::CODE | Ip6::CODE => match it.next() {
+                Some(t) if t.code() == Tcp::CODE => {}
+                _ => {
+                    return Some(format!(
+                        "{} must be followed by /tcp/<port>",
+                        proto_str(&p)
+                    ))
+                }
+            },
+            DnsAddr::CODE => {
+                if matches!(it.peek(), Some(t) if t.code() == Tcp::CODE) {
+                    let _ = it.next();
+                }
+            }
+            Service::CODE | Secure::CODE => {}
+            _ => {
+                return Some(format!(
+                    "{} is not supported here, expected one of /ip4, /ip6, /dnsaddr, /service or /secure",
+                    proto_str(&p)
+                ))
+            }
+        }
+    }
+    None
+}
+
+/// Render a single protocol of a MultiAddr, e.g. `/ip4/127.0.0.1`.
+fn proto_str(p: &ProtoValue) -> String {
+    let mut ma = MultiAddr::default();
+    match ma.push_back_value(p) {
+        Ok(()) => ma.to_string(),
+        Err(_) => format!("protocol code {}", p.code()),
+    }
+}
+
+/// Read one subject id per line, skipping blank lines.
+fn read_ids(r: impl BufRead) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for line in r.lines() {
+        let line = line.map_err(|e| Error::new(exitcode::IOERR, e.into()))?;
+        let id = line.trim();
+        if !id.is_empty() {
+            ids.push(id.to_string())
+        }
+    }
+    Ok(ids)
+}
+
+/// Render an attribute value, `null` in JSON if it is missing.
+fn format_value(val: Option<&[u8]>, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Plain => format!("{val:?}"),
+        OutputFormat::Json => json!(val.map(String::from_utf8_lossy)).to_string(),
+    }
+}
+
+/// Render all attributes of a subject, one `key: value` line each in plain text.
+fn format_list(attrs: &BTreeMap<String, Vec<u8>>, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Plain => attrs
+            .iter()
+            .map(|(k, v)| format!("{k}: {}", String::from_utf8_lossy(v)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Json => {
+            let m: BTreeMap<&str, Cow<str>> = attrs
+                .iter()
+                .map(|(k, v)| (k.as_str(), String::from_utf8_lossy(v)))
+                .collect();
+            json!(m).to_string()
+        }
+    }
+}
+
+/// Render what `verb` did to attribute `key` of `id`, e.g. `Set`.
+fn format_change(verb: &str, id: &str, key: &str, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Plain => format!("{verb} attribute {key} of {id}"),
+        OutputFormat::Json => {
+            json!({"id": id, "key": key, "status": verb.to_lowercase()}).to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_format_value_json() {
+        let s = format_value(Some(b"bar"), &OutputFormat::Json);
+        assert_eq!(serde_json::from_str::<Value>(&s).unwrap(), json!("bar"));
+
+        let s = format_value(None, &OutputFormat::Json);
+        assert_eq!(serde_json::from_str::<Value>(&s).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_unroutable_reason() {
+        let ma = MultiAddr::from_str("/ip4/127.0.0.1/tcp/4000/service/auth").unwrap();
+        assert_eq!(unroutable_reason(&ma), None);
+
+        let ma = MultiAddr::from_str("/ip4/127.0.0.1").unwrap();
+        let reason = unroutable_reason(&ma).unwrap();
+        assert!(reason.contains("/ip4/127.0.0.1 must be followed by /tcp"));
+
+        let ma = MultiAddr::from_str("/tcp/4000/ip4/127.0.0.1").unwrap();
+        let reason = unroutable_reason(&ma).unwrap();
+        assert!(reason.starts_with("/tcp/4000 is not supported"));
+    }
+
+    #[test]
+    fn test_read_ids() {
+        let ids = read_ids("id1\n\n id2 \n".as_bytes()).unwrap();
+        assert_eq!(ids, vec!["id1", "id2"]);
+    }
+
+    #[test]
+    fn test_format_list() {
+        let mut attrs = BTreeMap::new();
+        attrs.insert("a".to_string(), b"x".to_vec());
+        attrs.insert("b".to_string(), b"y".to_vec());
+        assert_eq!(format_list(&attrs, &OutputFormat::Plain), "a: x\nb: y");
+        let s = format_list(&attrs, &OutputFormat::Json);
+        assert_eq!(
+            serde_json::from_str::<Value>(&s).unwrap(),
+            json!({"a": "x", "b": "y"})
+        );
+        assert_eq!(format_list(&BTreeMap::new(), &OutputFormat::Json), "{}");
+    }
+
+    #[test]
+    fn test_format_change() {
+        let s = format_change("Deleted", "foo", "a", &OutputFormat::Plain);
+        assert_eq!(s, "Deleted attribute a of foo");
+        let s = format_change("Deleted", "foo", "a", &OutputFormat::Json);
+        assert_eq!(
+            serde_json::from_str::<Value>(&s).unwrap(),
+            json!({"id": "foo", "key": "a", "status": "deleted"})
+        );
+    }
+
+    #[test]
+    fn test_format_value_plain() {
+        assert_eq!(format_value(None, &OutputFormat::Plain), "None");
+    }
+}
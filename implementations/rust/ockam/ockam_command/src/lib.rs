@@ -185,7 +185,7 @@ pub struct GlobalArgs {
     #[arg(global = true, long, short, conflicts_with("verbose"))]
     quiet: bool,
 
-    /// Increase verbosity of trace messages
+    /// Increase verbosity of trace messages: -v info, -vv debug, -vvv trace
     #[arg(
         global = true,
         long,
@@ -358,7 +358,7 @@ impl OckamCommand {
         }
 
         match self.subcommand {
-            OckamSubcommand::Authenticated(c) => c.run(),
+            OckamSubcommand::Authenticated(c) => c.run(options),
             OckamSubcommand::Configuration(c) => c.run(options),
             OckamSubcommand::Enroll(c) => c.run(options),
             OckamSubcommand::Forwarder(c) => c.run(options),
@@ -381,10 +381,34 @@ pub fn find_available_port() -> Result<u16> {
 }
 
 pub fn setup_logging(verbose: u8, no_color: bool) {
+    let filter = match log_filter(verbose) {
+        Some(filter) => filter,
+        None => return,
+    };
+    let fmt = fmt::Layer::default().with_ansi(!no_color);
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_error::ErrorLayer::default())
+        .with(fmt)
+        .try_init();
+    if result.is_err() {
+        eprintln!("Failed to initialise tracing logging.");
+    }
+}
+
+/// The log filter for the ockam crates at the level set by `-v`, `None` if
+/// logging is disabled.
+///
+/// If `verbose` is not set, try to read the log level from the OCKAM_LOG env variable.
+/// If both `verbose` and OCKAM_LOG are not set, logging will not be enabled.
+/// Otherwise, use `verbose` to define the log level: `-v` is info, `-vv` is debug
+/// and `-vvv` is trace.
+fn log_filter(verbose: u8) -> Option<EnvFilter> {
     let ockam_crates = [
         "ockam",
         "ockam_node",
         "ockam_core",
+        "ockam_api",
         "ockam_command",
         "ockam_identity",
         "ockam_channel",
@@ -393,13 +417,10 @@ pub fn setup_logging(verbose: u8, no_color: bool) {
         "ockam_vault_sync_core",
     ];
     let builder = EnvFilter::builder();
-    // If `verbose` is not set, try to read the log level from the OCKAM_LOG env variable.
-    // If both `verbose` and OCKAM_LOG are not set, logging will not be enabled.
-    // Otherwise, use `verbose` to define the log level.
     let filter = match verbose {
         0 => match env::var("OCKAM_LOG") {
             Ok(s) if !s.is_empty() => builder.with_env_var("OCKAM_LOG").from_env_lossy(),
-            _ => return,
+            _ => return None,
         },
         1 => builder
             .with_default_directive(LevelFilter::INFO.into())
@@ -411,15 +432,7 @@ pub fn setup_logging(verbose: u8, no_color: bool) {
             .with_default_directive(LevelFilter::TRACE.into())
             .parse_lossy(ockam_crates.map(|c| format!("{c}=trace")).join(",")),
     };
-    let fmt = fmt::Layer::default().with_ansi(!no_color);
-    let result = tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_error::ErrorLayer::default())
-        .with(fmt)
-        .try_init();
-    if result.is_err() {
-        eprintln!("Failed to initialise tracing logging.");
-    }
+    Some(filter)
 }
 
 #[allow(unused)]
@@ -556,6 +569,15 @@ mod tests {
     use super::*;
     use ockam_core::compat::net::SocketAddr;
 
+    #[test]
+    fn test_log_filter_levels() {
+        let level = |verbose| log_filter(verbose).unwrap().max_level_hint();
+        assert_eq!(level(1), Some(LevelFilter::INFO));
+        assert_eq!(level(2), Some(LevelFilter::DEBUG));
+        assert_eq!(level(3), Some(LevelFilter::TRACE));
+        assert_eq!(level(9), Some(LevelFilter::TRACE));
+    }
+
     #[test]
     fn test_extract_address_value() {
         let test_cases = vec![
@@ -1,5 +1,9 @@
 use assert_cmd::prelude::*;
+use ockam::authenticated_storage::{AuthenticatedStorage, InMemoryStorage};
+use ockam::{Context, TcpTransport};
+use ockam_api::auth;
 use std::process::Command;
+use std::time::Duration;
 
 #[test]
 fn valid_arguments() -> Result<(), Box<dyn std::error::Error>> {
@@ -7,16 +11,75 @@ fn valid_arguments() -> Result<(), Box<dyn std::error::Error>> {
     cmd.arg("--test-argument-parser")
         .arg("authenticated")
         .arg("get")
+        .arg("--addr")
         .arg("/ip4/127.0.0.1/tcp/8080")
         .arg("--id")
         .arg("identifier")
         .arg("key");
     cmd.assert().success();
 
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1/tcp/8080")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key")
+        .arg("--output")
+        .arg("json");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1/tcp/8080")
+        .arg("--id")
+        .arg("-")
+        .arg("key");
+    cmd.assert().success();
+
     let mut cmd = Command::cargo_bin("ockam")?;
     cmd.arg("--test-argument-parser")
         .arg("authenticated")
         .arg("del")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1/tcp/8080")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("authenticated")
+        .arg("list")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1/tcp/8080")
+        .arg("--id")
+        .arg("identifier");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("authenticated")
+        .arg("set")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1/tcp/8080")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key")
+        .arg("value");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("authenticated")
+        .arg("delete")
+        .arg("--addr")
         .arg("/ip4/127.0.0.1/tcp/8080")
         .arg("--id")
         .arg("identifier")
@@ -25,3 +88,170 @@ fn valid_arguments() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn unroutable_address_fails() -> Result<(), Box<dyn std::error::Error>> {
+    // A MultiAddr without a transport can't be turned into a route
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.1")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key");
+    cmd.assert().failure().code(64);
+
+    Ok(())
+}
+
+#[test]
+fn unreachable_address_fails_within_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg("/ip4/10.255.255.1/tcp/4000")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key")
+        .arg("--timeout")
+        .arg("1")
+        .arg("--retries")
+        .arg("1");
+    cmd.timeout(Duration::from_secs(20)).assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn refused_connection_is_unavailable() -> Result<(), Box<dyn std::error::Error>> {
+    // Nothing listens on a port that was just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg(format!("/ip4/127.0.0.1/tcp/{port}"))
+        .arg("--id")
+        .arg("identifier")
+        .arg("key")
+        .arg("--timeout")
+        .arg("1");
+    cmd.timeout(Duration::from_secs(20))
+        .assert()
+        .failure()
+        .code(69);
+
+    Ok(())
+}
+
+#[test]
+fn bad_addresses_are_explained() -> Result<(), Box<dyn std::error::Error>> {
+    let stderr = |addr: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("ockam")?;
+        cmd.arg("authenticated")
+            .arg("get")
+            .arg("--addr")
+            .arg(addr)
+            .arg("--id")
+            .arg("identifier")
+            .arg("key");
+        let output = cmd.output()?;
+        assert_eq!(output.status.code(), Some(64));
+        Ok(String::from_utf8(output.stderr)?)
+    };
+
+    assert!(stderr("/ip4/not-an-ip/tcp/4000")?.contains("is not a valid MultiAddr"));
+    assert!(stderr("/tcp/4000/ip4/127.0.0.1")?.contains("/tcp/4000 is not supported"));
+
+    Ok(())
+}
+
+#[test]
+fn address_falls_back_to_env() -> Result<(), Box<dyn std::error::Error>> {
+    // The address from the environment is used, and rejected, when --addr is omitted
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.env("OCKAM_ADDR", "/ip4/127.0.0.1")
+        .arg("authenticated")
+        .arg("get")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key");
+    let output = cmd.output()?;
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8(output.stderr)?.contains("/ip4/127.0.0.1 can't be routed to"));
+
+    // --addr takes precedence over the environment
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.env("OCKAM_ADDR", "/ip4/127.0.0.1/tcp/8080")
+        .arg("authenticated")
+        .arg("get")
+        .arg("--addr")
+        .arg("/ip4/127.0.0.2")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key");
+    let output = cmd.output()?;
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8(output.stderr)?.contains("/ip4/127.0.0.2 can't be routed to"));
+
+    // Without either the command fails
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.env_remove("OCKAM_ADDR")
+        .arg("authenticated")
+        .arg("get")
+        .arg("--id")
+        .arg("identifier")
+        .arg("key");
+    let output = cmd.output()?;
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8(output.stderr)?.contains("pass --addr or set OCKAM_ADDR"));
+
+    Ok(())
+}
+
+#[ockam_macros::test(crate = "ockam")]
+async fn get_reads_ids_from_stdin(ctx: &mut Context) -> ockam::Result<()> {
+    let s = InMemoryStorage::new();
+    s.set("id1", "key".to_string(), b"hello".to_vec()).await?;
+    s.set("id2", "key".to_string(), b"world".to_vec()).await?;
+    ctx.start_worker("auth", auth::Server::new(s)).await?;
+    let tcp = TcpTransport::create(ctx).await?;
+    let listener = tcp.listen("127.0.0.1:0").await?;
+
+    let home = tempfile::tempdir().unwrap();
+    let addr = format!("/ip4/127.0.0.1/tcp/{}/service/auth", listener.port());
+    let output = tokio::task::spawn_blocking(move || {
+        Command::cargo_bin("ockam")
+            .unwrap()
+            .env("OCKAM_HOME", home.path())
+            .arg("authenticated")
+            .arg("get")
+            .arg("--addr")
+            .arg(addr)
+            .arg("--id")
+            .arg("-")
+            .arg("key")
+            .arg("--output")
+            .arg("json")
+            .write_stdin("id1\nid2\n")
+            .timeout(Duration::from_secs(20))
+            .output()
+            .unwrap()
+    })
+    .await
+    .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        vec![r#""hello""#, r#""world""#],
+        stdout.lines().collect::<Vec<_>>()
+    );
+
+    ctx.stop().await
+}
@@ -4,6 +4,10 @@ use core::fmt::Debug;
 
 /// Defines the interface for message flow authorization.
 ///
+/// Policies get the whole [`RelayMessage`], so besides the source and
+/// destination addresses they can look at the message's routes, its
+/// `LocalInfo` and its payload, see e.g. [`MaxPayloadSize`].
+///
 /// # Examples
 ///
 /// ```
@@ -32,6 +36,21 @@ pub trait AccessControl: Debug + Send + Sync + 'static {
     async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool>;
 }
 
+/// Combinators to compose [`AccessControl`]s
+pub trait AccessControlExt: AccessControl + Sized {
+    /// Allow messages allowed by both `self` and `other`
+    fn and<O: AccessControl>(self, other: O) -> AllAccessControl<Self, O> {
+        AllAccessControl::new(self, other)
+    }
+
+    /// Allow messages allowed by either `self` or `other`
+    fn or<O: AccessControl>(self, other: O) -> AnyAccessControl<Self, O> {
+        AnyAccessControl::new(self, other)
+    }
+}
+
+impl<T: AccessControl> AccessControlExt for T {}
+
 /// Convenience structure for passing around an incoming/outgoing
 /// [`AccessControl`] pair.
 pub struct AccessControlPair<IN, OUT> {
@@ -41,15 +60,21 @@ pub struct AccessControlPair<IN, OUT> {
     pub outgoing: OUT,
 }
 
+mod addresses;
 mod all;
 mod allow_all;
 mod any;
 mod deny_all;
+mod logging;
+mod payload;
 
+pub use addresses::*;
 pub use all::*;
 pub use allow_all::*;
 pub use any::*;
 pub use deny_all::*;
+pub use logging::*;
+pub use payload::*;
 
 use crate::Address;
 
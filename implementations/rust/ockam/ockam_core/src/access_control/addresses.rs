@@ -0,0 +1,114 @@
+use crate::access_control::AccessControl;
+use crate::compat::boxed::Box;
+use crate::compat::vec::Vec;
+use crate::{Address, RelayMessage, Result};
+
+/// The address a message comes from, i.e. the first hop of its return route.
+///
+/// For a message received over a transport this is the remote peer, e.g.
+/// `(TCP, "10.0.0.1:4000")`, for a local message the sending worker.
+fn from_address(relay_msg: &RelayMessage) -> Option<&Address> {
+    relay_msg.local_msg.transport().return_route.next().ok()
+}
+
+/// An Access Control type that allows messages coming from one of the
+/// given addresses to go through
+#[derive(Debug)]
+pub struct AllowFromAddresses(Vec<Address>);
+
+impl AllowFromAddresses {
+    /// Constructor
+    pub fn new<A: Into<Address>>(addresses: impl IntoIterator<Item = A>) -> Self {
+        Self(addresses.into_iter().map(Into::into).collect())
+    }
+}
+
+#[async_trait]
+impl AccessControl for AllowFromAddresses {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        match from_address(relay_msg) {
+            Some(from) if self.0.contains(from) => crate::allow(),
+            _ => crate::deny(),
+        }
+    }
+}
+
+/// An Access Control type that blocks messages coming from one of the
+/// given addresses and allows all others
+#[derive(Debug)]
+pub struct DenyFromAddresses(Vec<Address>);
+
+impl DenyFromAddresses {
+    /// Constructor
+    pub fn new<A: Into<Address>>(addresses: impl IntoIterator<Item = A>) -> Self {
+        Self(addresses.into_iter().map(Into::into).collect())
+    }
+}
+
+#[async_trait]
+impl AccessControl for DenyFromAddresses {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        match from_address(relay_msg) {
+            Some(from) if self.0.contains(from) => crate::deny(),
+            _ => crate::allow(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use crate::compat::future::poll_once;
+    use crate::{
+        route, Address, LocalMessage, RelayMessage, Result, TransportMessage, TransportType,
+    };
+    use crate::{AccessControl, AccessControlExt, AllowAll, DenyAll};
+
+    use super::{AllowFromAddresses, DenyFromAddresses};
+
+    fn check(ac: impl AccessControl, from: Address) -> Result<bool> {
+        poll_once(async {
+            let local_message =
+                LocalMessage::new(TransportMessage::v1(route![], route![from], vec![]), vec![]);
+            let relay_message = RelayMessage::new(
+                Address::random_local(),
+                Address::random_local(),
+                local_message,
+                route![],
+                false,
+            );
+            ac.is_authorized(&relay_message).await
+        })
+    }
+
+    fn peer(addr: &str) -> Address {
+        Address::new(TransportType::new(1), addr)
+    }
+
+    #[test]
+    fn test_allow_from_addresses() {
+        let ac = || AllowFromAddresses::new([peer("10.0.0.1:4000"), peer("10.0.0.2:4000")]);
+        assert!(check(ac(), peer("10.0.0.2:4000")).unwrap());
+        assert!(!check(ac(), peer("10.0.0.3:4000")).unwrap());
+    }
+
+    #[test]
+    fn test_deny_from_addresses() {
+        let ac = || DenyFromAddresses::new([peer("10.0.0.1:4000")]);
+        assert!(!check(ac(), peer("10.0.0.1:4000")).unwrap());
+        assert!(check(ac(), peer("10.0.0.3:4000")).unwrap());
+    }
+
+    #[test]
+    fn test_composed() {
+        let ac = || {
+            AllowFromAddresses::new([peer("10.0.0.1:4000"), peer("10.0.0.2:4000")])
+                .and(DenyFromAddresses::new([peer("10.0.0.2:4000")]))
+        };
+        assert!(check(ac(), peer("10.0.0.1:4000")).unwrap());
+        assert!(!check(ac(), peer("10.0.0.2:4000")).unwrap());
+
+        assert!(check(DenyAll.or(AllowAll), peer("10.0.0.1:4000")).unwrap());
+        assert!(!check(DenyAll.and(AllowAll), peer("10.0.0.1:4000")).unwrap());
+    }
+}
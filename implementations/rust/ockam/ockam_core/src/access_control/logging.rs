@@ -0,0 +1,127 @@
+use crate::access_control::AccessControl;
+use crate::compat::boxed::Box;
+use crate::compat::sync::Arc;
+use crate::{RelayMessage, Result};
+use tracing::{debug, warn};
+
+/// An Access Control type that delegates to an inner `AccessControl`
+/// and logs its decision
+///
+/// Allowed messages are logged at `debug`, denied messages at `warn`,
+/// both with the source, destination and routes of the message. The
+/// decision of the inner `AccessControl` is returned unchanged.
+#[derive(Debug)]
+pub struct LoggingAccessControl {
+    inner: Arc<dyn AccessControl>,
+}
+
+impl LoggingAccessControl {
+    /// Constructor
+    pub fn new(inner: Arc<dyn AccessControl>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl AccessControl for LoggingAccessControl {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        let transport = relay_msg.local_msg.transport();
+        let allowed = self.inner.is_authorized(relay_msg).await?;
+        if allowed {
+            debug!(
+                source = %relay_msg.source,
+                destination = %relay_msg.destination,
+                onward_route = %transport.onward_route,
+                return_route = %transport.return_route,
+                "message allowed by access control"
+            );
+        } else {
+            warn!(
+                source = %relay_msg.source,
+                destination = %relay_msg.destination,
+                onward_route = %transport.onward_route,
+                return_route = %transport.return_route,
+                "message denied by access control"
+            );
+        }
+        Ok(allowed)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod tests {
+    use crate::compat::future::poll_once;
+    use crate::compat::sync::Arc;
+    use crate::{route, Address, LocalMessage, RelayMessage, TransportMessage};
+    use crate::{AccessControl, AllowAll, DenyAll};
+    use std::io;
+    use std::sync::Mutex;
+
+    use super::LoggingAccessControl;
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `ac` against a message and return its decision and what was logged
+    fn check(ac: Arc<dyn AccessControl>) -> (bool, String) {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let allowed = tracing::subscriber::with_default(subscriber, || {
+            poll_once(async {
+                let local_message = LocalMessage::new(
+                    TransportMessage::v1(route!["app"], route!["sender"], vec![]),
+                    vec![],
+                );
+                let relay_message = RelayMessage::new(
+                    Address::from_string("sender"),
+                    Address::from_string("app"),
+                    local_message,
+                    route!["app"],
+                    false,
+                );
+                LoggingAccessControl::new(ac)
+                    .is_authorized(&relay_message)
+                    .await
+            })
+        })
+        .unwrap();
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        (allowed, logs)
+    }
+
+    #[test]
+    fn test_logs_allowed() {
+        let (allowed, logs) = check(Arc::new(AllowAll));
+        assert!(allowed);
+        assert!(logs.contains("DEBUG"));
+        assert!(logs.contains("message allowed by access control"));
+        assert!(logs.contains("source=0#sender"));
+    }
+
+    #[test]
+    fn test_logs_denied() {
+        let (allowed, logs) = check(Arc::new(DenyAll));
+        assert!(!allowed);
+        assert!(logs.contains("WARN"));
+        assert!(logs.contains("message denied by access control"));
+        assert!(logs.contains("destination=0#app"));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::access_control::AccessControl;
+use crate::compat::boxed::Box;
+use crate::{RelayMessage, Result};
+
+/// An Access Control type that allows messages whose payload is at
+/// most the given number of bytes
+#[derive(Debug)]
+pub struct MaxPayloadSize(pub usize);
+
+#[async_trait]
+impl AccessControl for MaxPayloadSize {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        if relay_msg.local_msg.transport().payload.len() <= self.0 {
+            crate::allow()
+        } else {
+            crate::deny()
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use crate::compat::future::poll_once;
+    use crate::{route, Address, LocalMessage, RelayMessage, Result, TransportMessage};
+    use crate::{AccessControl, AccessControlExt, DenyAll};
+
+    use super::MaxPayloadSize;
+
+    fn check(ac: impl AccessControl, payload_len: usize) -> Result<bool> {
+        poll_once(async {
+            let local_message = LocalMessage::new(
+                TransportMessage::v1(route!["app"], route![], vec![0; payload_len]),
+                vec![],
+            );
+            let relay_message = RelayMessage::new(
+                Address::random_local(),
+                Address::from_string("app"),
+                local_message,
+                route!["app"],
+                false,
+            );
+            ac.is_authorized(&relay_message).await
+        })
+    }
+
+    #[test]
+    fn test_max_payload_size() {
+        assert!(check(MaxPayloadSize(16), 0).unwrap());
+        assert!(check(MaxPayloadSize(16), 16).unwrap());
+        assert!(!check(MaxPayloadSize(16), 17).unwrap());
+    }
+
+    #[test]
+    fn test_max_payload_size_composed() {
+        assert!(!check(MaxPayloadSize(16).and(DenyAll), 1).unwrap());
+        assert!(check(DenyAll.or(MaxPayloadSize(16)), 1).unwrap());
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::compat::borrow::Cow;
 use crate::compat::rand;
+use crate::compat::string::String;
 use crate::compat::vec::Vec;
 use crate::errcode::{Kind, Origin};
 use crate::Result;
@@ -15,6 +16,11 @@ use crate::TypeTag;
 
 pub const SCHEMA: &str = core::include_str!("schema.cddl");
 
+/// The version of the request/response protocol spoken by this crate.
+///
+/// Bump it when the wire format changes in a way older peers can't handle.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// A request header.
 #[derive(Debug, Clone, Encode, Decode)]
 #[rustfmt::skip]
@@ -34,12 +40,16 @@ pub struct Request<'a> {
     #[b(2)] path: Cow<'a, str>,
     /// The request method.
     ///
-    /// It is wrapped in an `Option` to be forwards compatible, i.e. adding
-    /// methods will not cause decoding errors and client code can decide
-    /// how to handle unknown methods.
-    #[n(3)] method: Option<Method>,
+    /// It is kept as the raw method code to be forwards compatible, i.e.
+    /// adding methods will not cause decoding errors and server code can
+    /// tell a missing method apart from one it does not know.
+    #[n(3)] method: Option<u32>,
     /// Indicator if a request body is expected after this header.
-    #[n(4)] has_body: bool
+    #[n(4)] has_body: bool,
+    /// The protocol version of the client.
+    ///
+    /// Missing in requests of clients that predate versioning.
+    #[n(5)] version: Option<u32>
 }
 
 /// The response header.
@@ -75,19 +85,47 @@ pub fn unknown_path<'a>(r: &'a Request) -> ResponseBuilder<Error<'a>> {
 }
 
 /// Create an error response because the request method was unknown or not allowed.
+///
+/// A request without a method gets `NotImplemented`. A request whose
+/// method is known but not allowed, or whose method code this version
+/// does not know, gets `MethodNotAllowed` and the code is echoed back.
 pub fn invalid_method<'a>(r: &'a Request) -> ResponseBuilder<Error<'a>> {
-    match r.method() {
-        Some(m) => {
+    match (r.method(), r.method_code()) {
+        (Some(m), _) => {
             let e = Error::new(r.path()).with_method(m);
             Response::builder(r.id(), Status::MethodNotAllowed).body(e)
         }
-        None => {
-            let e = Error::new(r.path()).with_message("unknown method");
+        (None, Some(code)) => {
+            let e = Error::new(r.path())
+                .with_message(crate::compat::format!("unknown method code {code}"));
+            Response::builder(r.id(), Status::MethodNotAllowed).body(e)
+        }
+        (None, None) => {
+            let e = Error::new(r.path()).with_message("missing method");
             Response::not_implemented(r.id()).body(e)
         }
     }
 }
 
+/// Create an error response if the request uses a newer protocol version than this one.
+///
+/// Requests without a version are accepted, they come from clients that
+/// predate versioning.
+pub fn check_version<'a>(r: &'a Request) -> Option<ResponseBuilder<Error<'a>>> {
+    match r.version() {
+        Some(v) if v > PROTOCOL_VERSION => {
+            let mut e = Error::new(r.path()).with_message(crate::compat::format!(
+                "unsupported protocol version {v}, supported up to {PROTOCOL_VERSION}"
+            ));
+            if let Some(m) = r.method() {
+                e = e.with_method(m)
+            }
+            Some(Response::builder(r.id(), Status::VersionNotSupported).body(e))
+        }
+        _ => None,
+    }
+}
+
 /// Create an error response with status forbidden and the given message.
 pub fn forbidden<'a>(r: &'a Request, m: &'a str) -> ResponseBuilder<Error<'a>> {
     let mut e = Error::new(r.path()).with_message(m);
@@ -132,6 +170,31 @@ pub enum Method {
     #[n(4)] Patch
 }
 
+impl Method {
+    /// The code of this method on the wire
+    pub fn code(self) -> u32 {
+        match self {
+            Self::Get => 0,
+            Self::Post => 1,
+            Self::Put => 2,
+            Self::Delete => 3,
+            Self::Patch => 4,
+        }
+    }
+
+    /// The method with the given code, if this version knows it
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Get),
+            1 => Some(Self::Post),
+            2 => Some(Self::Put),
+            3 => Some(Self::Delete),
+            4 => Some(Self::Patch),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Method {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(match self {
@@ -151,6 +214,7 @@ impl Display for Method {
 #[cbor(index_only)]
 pub enum Status {
     #[n(200)] Ok,
+    #[n(204)] NoContent,
     #[n(400)] BadRequest,
     #[n(401)] Unauthorized,
     #[n(403)] Forbidden,
@@ -158,13 +222,15 @@ pub enum Status {
     #[n(409)] Conflict,
     #[n(405)] MethodNotAllowed,
     #[n(500)] InternalServerError,
-    #[n(501)] NotImplemented
+    #[n(501)] NotImplemented,
+    #[n(505)] VersionNotSupported
 }
 
 impl Display for Status {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.write_str(match self {
             Status::Ok => "200 Ok",
+            Status::NoContent => "204 NoContent",
             Status::BadRequest => "400 BadRequest",
             Status::Unauthorized => "401 Unauthorized",
             Status::Forbidden => "403 Forbidden",
@@ -173,6 +239,7 @@ impl Display for Status {
             Status::MethodNotAllowed => "405 MethodNotAllowed",
             Status::InternalServerError => "500 InternalServerError",
             Status::NotImplemented => "501 NotImplemented",
+            Status::VersionNotSupported => "505 VersionNotSupported",
         })
     }
 }
@@ -202,9 +269,10 @@ impl<'a> Request<'a> {
             #[cfg(feature = "tag")]
             tag: TypeTag,
             id: Id::fresh(),
-            method: Some(method),
+            method: Some(method.code()),
             path: path.into(),
             has_body,
+            version: Some(PROTOCOL_VERSION),
         }
     }
 
@@ -243,14 +311,49 @@ impl<'a> Request<'a> {
         &self.path
     }
 
+    /// The path segments, without the query string.
     pub fn path_segments<const N: usize>(&self) -> Segments<N> {
-        Segments::parse(self.path())
+        let path = match self.path.split_once('?') {
+            Some((path, _)) => path,
+            None => self.path(),
+        };
+        Segments::parse(path)
+    }
+
+    /// The part of the path after the first `?`, if any.
+    pub fn query(&self) -> Option<&str> {
+        self.path.split_once('?').map(|(_, q)| q)
+    }
+
+    /// The percent-decoded `key=value` pairs of the query string.
+    ///
+    /// A pair without `=` has an empty value, a missing query string
+    /// yields no pairs.
+    pub fn query_pairs(&self) -> impl Iterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+        self.query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|kv| !kv.is_empty())
+            .map(|kv| {
+                let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+                (percent_decode(k), percent_decode(v))
+            })
     }
 
     pub fn method(&self) -> Option<Method> {
+        self.method.and_then(Method::from_code)
+    }
+
+    /// The raw method code, which may be unknown to this version
+    pub fn method_code(&self) -> Option<u32> {
         self.method
     }
 
+    /// The protocol version of the client, if it sent one
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
     pub fn has_body(&self) -> bool {
         self.has_body
     }
@@ -279,6 +382,11 @@ impl Response {
         Response::builder(re, Status::Ok)
     }
 
+    /// A success without a body, e.g. for a delete.
+    pub fn no_content(re: Id) -> ResponseBuilder {
+        Response::builder(re, Status::NoContent)
+    }
+
     pub fn bad_request(re: Id) -> ResponseBuilder {
         Response::builder(re, Status::BadRequest)
     }
@@ -396,6 +504,51 @@ impl<'a, const N: usize> Segments<'a, N> {
     }
 }
 
+/// Append `s` to `out`, escaping everything but unreserved characters as `%XY`.
+fn percent_encode(s: &str, out: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            b => {
+                out.push('%');
+                out.push(HEX[usize::from(b >> 4)] as char);
+                out.push(HEX[usize::from(b & 0xf)] as char);
+            }
+        }
+    }
+}
+
+/// Decode `%XY` escapes and `+` as space, keeping malformed escapes as is.
+fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.bytes().any(|b| b == b'%' || b == b'+') {
+        return Cow::Borrowed(s);
+    }
+    fn hex(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(h), Some(l)) => {
+                    out.push(h << 4 | l);
+                    i += 2
+                }
+                _ => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
 #[derive(Debug)]
 pub struct RequestBuilder<'a, T = ()> {
     header: Request<'a>,
@@ -414,10 +567,39 @@ impl<'a, T> RequestBuilder<'a, T> {
     }
 
     pub fn method(mut self, m: Method) -> Self {
-        self.header.method = Some(m);
+        self.header.method = Some(m.code());
+        self
+    }
+
+    pub fn version(mut self, v: u32) -> Self {
+        self.header.version = Some(v);
         self
     }
 
+    /// Append a percent-encoded `key=value` pair to the path's query string.
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        let path = self.header.path.to_mut();
+        match path.find('?') {
+            None => path.push('?'),
+            Some(i) if i + 1 == path.len() || path.ends_with('&') => {}
+            Some(_) => path.push('&'),
+        }
+        percent_encode(key, path);
+        path.push('=');
+        percent_encode(value, path);
+        self
+    }
+
+    /// Append several percent-encoded `key=value` pairs, see [`Self::query`].
+    pub fn query_pairs<'k, I>(self, pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (&'k str, &'k str)>,
+    {
+        pairs
+            .into_iter()
+            .fold(self, |this, (k, v)| this.query(k, v))
+    }
+
     pub fn header(&self) -> &Request<'a> {
         &self.header
     }
@@ -569,7 +751,7 @@ pub fn is_ok(label: &str, buf: &[u8]) -> Result<()> {
     let mut d = Decoder::new(buf);
     let res = response(label, &mut d)?;
     assert_response_match(None, buf);
-    if res.status() == Some(Status::Ok) {
+    if matches!(res.status(), Some(Status::Ok | Status::NoContent)) {
         Ok(())
     } else {
         Err(error(label, &res, &mut d))
@@ -652,3 +834,138 @@ impl<C> Encode<C> for Cbor<'_> {
             .map_err(encode::Error::write)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_codes_roundtrip() {
+        for m in [
+            Method::Get,
+            Method::Post,
+            Method::Put,
+            Method::Delete,
+            Method::Patch,
+        ] {
+            assert_eq!(
+                Method::from_code(m.code()).map(|m| m.code()),
+                Some(m.code())
+            );
+            let expected = minicbor::to_vec(m).unwrap();
+            assert_eq!(minicbor::to_vec(m.code()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn unknown_method_is_not_absent() {
+        let mut req = Request::new(Method::Get, "/", false);
+        req.method = Some(42);
+        let bytes = minicbor::to_vec(&req).unwrap();
+        let mut req: Request = minicbor::decode(&bytes).unwrap();
+        assert!(req.method().is_none());
+        assert_eq!(req.method_code(), Some(42));
+        assert_eq!(
+            invalid_method(&req).header().status(),
+            Some(Status::MethodNotAllowed)
+        );
+
+        req.method = None;
+        assert_eq!(
+            invalid_method(&req).header().status(),
+            Some(Status::NotImplemented)
+        );
+    }
+
+    fn pairs(path: &str) -> Vec<(String, String)> {
+        Request::new(Method::Get, path, false)
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn query_pairs_empty() {
+        assert!(pairs("/nodes").is_empty());
+        assert!(pairs("/nodes?").is_empty());
+    }
+
+    #[test]
+    fn query_pairs_single_and_multi() {
+        assert_eq!(pairs("/nodes?limit=10"), [("limit".into(), "10".into())]);
+        assert_eq!(
+            pairs("/nodes?limit=10&&offset=20&all"),
+            [
+                ("limit".into(), "10".into()),
+                ("offset".into(), "20".into()),
+                ("all".into(), "".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn query_pairs_percent_decoding() {
+        assert_eq!(
+            pairs("/nodes?name=a%20b+c&x%3Dy=100%25&bad=%zz%4"),
+            [
+                ("name".into(), "a b c".into()),
+                ("x=y".into(), "100%".into()),
+                ("bad".into(), "%zz%4".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn query_builder_encodes_special_characters() {
+        let req = Request::get("/nodes")
+            .query("name", "a b&c=d/é")
+            .query("100%", "");
+        assert_eq!(
+            req.header().path(),
+            "/nodes?name=a%20b%26c%3Dd%2F%C3%A9&100%25="
+        );
+        assert_eq!(
+            pairs(req.header().path()),
+            [
+                ("name".into(), "a b&c=d/é".into()),
+                ("100%".into(), "".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn query_builder_appends_to_existing_query() {
+        let req = Request::get("/nodes?all").query_pairs([("limit", "10"), ("offset", "20")]);
+        assert_eq!(req.header().path(), "/nodes?all&limit=10&offset=20");
+
+        let req = Request::get("/nodes?").query("limit", "10");
+        assert_eq!(req.header().path(), "/nodes?limit=10");
+
+        let req = Request::get("/nodes?all&").query("limit", "10");
+        assert_eq!(req.header().path(), "/nodes?all&limit=10");
+    }
+
+    #[test]
+    fn path_segments_ignore_query() {
+        let req = Request::new(Method::Get, "/node/n1?verbose=true", false);
+        assert_eq!(req.path_segments::<3>().as_slice(), ["node", "n1"]);
+        assert_eq!(req.query(), Some("verbose=true"));
+    }
+
+    #[test]
+    fn newer_protocol_version_is_rejected() {
+        let req = Request::get("/node").version(PROTOCOL_VERSION + 1);
+        let bytes = minicbor::to_vec(req.header()).unwrap();
+        let req: Request = minicbor::decode(&bytes).unwrap();
+        let res = check_version(&req).expect("version must be rejected");
+        assert_eq!(res.header().status(), Some(Status::VersionNotSupported));
+
+        let req = Request::new(Method::Get, "/node", false);
+        assert_eq!(req.version(), Some(PROTOCOL_VERSION));
+        assert!(check_version(&req).is_none());
+
+        let mut req = Request::new(Method::Get, "/node", false);
+        req.version = None;
+        assert!(check_version(&req).is_none());
+    }
+}
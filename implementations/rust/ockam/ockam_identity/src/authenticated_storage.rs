@@ -1,6 +1,7 @@
 use ockam_core::async_trait;
 use ockam_core::compat::{boxed::Box, string::String, vec::Vec};
-use ockam_core::{AsyncTryClone, Result};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{AsyncTryClone, Error, Result};
 
 /// Storage for Authenticated data
 #[async_trait]
@@ -13,6 +14,19 @@ pub trait AuthenticatedStorage: AsyncTryClone + Send + Sync + 'static {
 
     /// Delete entry
     async fn del(&self, id: &str, key: &str) -> Result<()>;
+
+    /// List all entries of an id
+    ///
+    /// Storages that can't enumerate their entries keep the default,
+    /// which fails with [`Kind::Unsupported`].
+    async fn list(&self, id: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let _ = id;
+        Err(Error::new(
+            Origin::Identity,
+            Kind::Unsupported,
+            "this authenticated storage can't list entries",
+        ))
+    }
 }
 
 /// In-memory impl
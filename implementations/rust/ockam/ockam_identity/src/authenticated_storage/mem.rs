@@ -57,4 +57,12 @@ impl AuthenticatedStorage for InMemoryStorage {
         }
         Ok(())
     }
+
+    async fn list(&self, id: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        let m = self.map.read().unwrap();
+        if let Some(a) = m.get(id) {
+            return Ok(a.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        }
+        Ok(Vec::new())
+    }
 }
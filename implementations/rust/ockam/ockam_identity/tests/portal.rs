@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use ockam_core::compat::sync::Arc;
+use ockam_core::{route, Result};
+use ockam_identity::access_control::IdentityIdAccessControl;
+use ockam_identity::authenticated_storage::mem::InMemoryStorage;
+use ockam_identity::{Identity, TrustIdentifierPolicy};
+use ockam_node::Context;
+use ockam_transport_tcp::{OutletOptions, TcpTransport};
+use ockam_vault::Vault;
+
+/// An Outlet that only opens portals for Alice, over a secure channel
+#[ockam_macros::test(timeout = 10000)]
+async fn outlet_requires_authenticated_ping(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let alice_vault = Vault::create();
+    let bob_vault = Vault::create();
+    let alice = Identity::create(ctx, &alice_vault).await?;
+    let bob = Identity::create(ctx, &bob_vault).await?;
+    let alice_storage = InMemoryStorage::new();
+    let bob_storage = InMemoryStorage::new();
+
+    bob.create_secure_channel_listener(
+        "bob_listener",
+        TrustIdentifierPolicy::new(alice.identifier().clone()),
+        &bob_storage,
+    )
+    .await?;
+    let alice_channel = alice
+        .create_secure_channel(
+            route!["bob_listener"],
+            TrustIdentifierPolicy::new(bob.identifier().clone()),
+            &alice_storage,
+        )
+        .await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer = listener.local_addr().unwrap().to_string();
+    let access_control = IdentityIdAccessControl::new(vec![alice.identifier().clone()]);
+    let options = OutletOptions::new("outlet".into(), peer, Arc::new(access_control));
+    tcp.create_outlet_extended(options).await?;
+
+    // Without a secure channel the `Ping` is refused and the peer never sees a connection
+    let (_, anonymous_inlet) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+    let _anonymous = TcpStream::connect(anonymous_inlet).await.unwrap();
+    let accepted = tokio::time::timeout(Duration::from_millis(500), listener.accept()).await;
+    assert!(accepted.is_err());
+
+    // Through Alice's secure channel the portal is opened
+    let (_, inlet) = tcp
+        .create_inlet("127.0.0.1:0", route![alice_channel, "outlet"])
+        .await?;
+    let mut stream = TcpStream::connect(inlet).await.unwrap();
+    let (mut peer_stream, _) = listener.accept().await.unwrap();
+
+    stream.write_all(b"hello").await.unwrap();
+    let mut buf = [0u8; 5];
+    peer_stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
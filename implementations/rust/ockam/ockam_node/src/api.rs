@@ -1,9 +1,14 @@
 use crate::Context;
+use crate::NodeError;
 use core::fmt::Display;
-use minicbor::Encode;
-use ockam_core::api::{assert_request_match, RequestBuilder};
+use core::future::Future;
+use core::time::Duration;
+use minicbor::{Decoder, Encode};
+use ockam_core::api::{assert_request_match, RequestBuilder, Response};
 use ockam_core::compat::vec::Vec;
-use ockam_core::{Address, LocalInfo, Result, Route};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Address, Error, LocalInfo, Result, Route};
+use ockam_transport_core::ReconnectStrategy;
 
 /// Encode request header and body (if any), send the package to the server and returns its response.
 pub async fn request<T, R>(
@@ -34,6 +39,55 @@ where
     Ok(vec)
 }
 
+/// Like [`request`], but wait at most `timeout` for the response.
+///
+/// The response is received by `ctx` itself, which should therefore
+/// not expect any other message. Late responses to earlier requests
+/// sent from `ctx` are discarded.
+pub async fn request_with_timeout<T, R>(
+    ctx: &mut Context,
+    label: &str,
+    struct_name: impl Into<Option<&str>>,
+    route: R,
+    req: RequestBuilder<'_, T>,
+    timeout: Duration,
+) -> Result<Vec<u8>>
+where
+    T: Encode<()>,
+    R: Into<Route> + Display,
+{
+    let mut buf = Vec::new();
+    req.encode(&mut buf)?;
+    assert_request_match(struct_name, &buf);
+    trace! {
+        target:  "ockam_api",
+        id     = %req.header().id(),
+        method = ?req.header().method(),
+        path   = %req.header().path(),
+        body   = %req.header().has_body(),
+        "-> {label}"
+    };
+    let id = req.header().id();
+    ctx.send(route, buf).await?;
+    crate::tokio::time::timeout(timeout, async {
+        loop {
+            let vec = ctx
+                .receive_duration_timeout::<Vec<u8>>(timeout)
+                .await?
+                .take()
+                .body();
+            match Decoder::new(&vec).decode::<Response>() {
+                Ok(res) if res.re() != id => {
+                    debug!(re = %res.re(), "{label}: discarding response to an earlier request")
+                }
+                _ => return Ok(vec),
+            }
+        }
+    })
+    .await
+    .map_err(|e| NodeError::Data.with_elapsed(e))?
+}
+
 /// Encode request header and body (if any), send the package to the server and returns its response.
 pub async fn request_with_local_info<T, R>(
     ctx: &mut Context,
@@ -68,3 +122,85 @@ where
 
     Ok((body, local_info))
 }
+
+/// Whether an API call that failed with `e` may succeed when tried again.
+///
+/// Only transport failures and timeouts are transient, errors like a
+/// bad request or a missing resource will not go away by themselves.
+pub fn is_transient(e: &Error) -> bool {
+    e.code().origin == Origin::Transport || e.code().kind == Kind::Timeout
+}
+
+/// Call `f` until it succeeds, fails with an error that is not
+/// [transient](is_transient) or `strategy` gives up.
+///
+/// The delay before every further attempt is taken from `strategy`.
+pub async fn retry<T, F, Fut>(label: &str, strategy: &dyn ReconnectStrategy, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if is_transient(&e) => match strategy.next_delay(attempt) {
+                Some(delay) => {
+                    debug!(%e, attempt, "{label} failed, retrying in {delay:?}");
+                    crate::tokio::time::sleep(delay).await;
+                    attempt += 1
+                }
+                None => return Err(e),
+            },
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use ockam_transport_core::{FixedReconnect, NoReconnect};
+
+    /// Fails with `kind` from `origin` on the first call, then succeeds.
+    async fn flaky(calls: &AtomicUsize, origin: Origin, kind: Kind) -> Result<&'static str> {
+        if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+            Err(Error::new_without_cause(origin, kind))
+        } else {
+            Ok("ok")
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_transient_error_succeeds() {
+        let calls = AtomicUsize::new(0);
+        let res = retry("test", &FixedReconnect::new(Duration::ZERO, 2), || {
+            flaky(&calls, Origin::Transport, Kind::Io)
+        })
+        .await;
+        assert_eq!(res.unwrap(), "ok");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_when_strategy_does() {
+        let calls = AtomicUsize::new(0);
+        let res = retry("test", &NoReconnect, || {
+            flaky(&calls, Origin::Node, Kind::Timeout)
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_permanent_error_fails() {
+        let calls = AtomicUsize::new(0);
+        let res = retry("test", &FixedReconnect::new(Duration::ZERO, 2), || {
+            flaky(&calls, Origin::Api, Kind::NotFound)
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -3,6 +3,7 @@ use crate::relay::CtrlSignal;
 use crate::tokio::runtime::Handle;
 use crate::{parser, Context};
 use core::marker::PhantomData;
+use core::time::Duration;
 use ockam_core::{Message, RelayMessage, Result, Routed, Worker};
 
 /// Worker relay machinery
@@ -17,6 +18,7 @@ where
 {
     worker: W,
     ctx: Context,
+    idle_timeout: Option<Duration>,
     _phantom: PhantomData<M>,
 }
 
@@ -25,10 +27,11 @@ where
     W: Worker<Context = Context, Message = M>,
     M: Message + Send + 'static,
 {
-    pub fn new(worker: W, ctx: Context) -> Self {
+    pub fn new(worker: W, ctx: Context, idle_timeout: Option<Duration>) -> Self {
         Self {
             worker,
             ctx,
+            idle_timeout,
             _phantom: PhantomData,
         }
     }
@@ -68,7 +71,25 @@ where
     /// Report errors as they occur, and signal whether the loop should
     /// continue running or not
     async fn recv_message(&mut self) -> Result<bool> {
-        let relay_msg = match self.ctx.receiver_next().await? {
+        let next = match self.idle_timeout {
+            Some(idle_timeout) => {
+                match crate::tokio::time::timeout(idle_timeout, self.ctx.receiver_next()).await {
+                    Ok(next) => next?,
+                    Err(_) => {
+                        debug!(
+                            "Worker {} was idle for {:?}, stopping",
+                            self.ctx.address(),
+                            idle_timeout
+                        );
+                        // Closes our mailbox, the next receive returns `None`
+                        self.ctx.stop_worker(self.ctx.address()).await?;
+                        return Ok(true);
+                    }
+                }
+            }
+            None => self.ctx.receiver_next().await?,
+        };
+        let relay_msg = match next {
             Some(msg) => msg,
             None => {
                 trace!("No more messages for worker {}", self.ctx.address());
@@ -176,8 +197,14 @@ where
     }
 
     /// Build and spawn a new worker relay, returning a send handle to it
-    pub(crate) fn init(rt: &Handle, worker: W, ctx: Context, ctrl_rx: SmallReceiver<CtrlSignal>) {
-        let relay = WorkerRelay::<W, M>::new(worker, ctx);
+    pub(crate) fn init(
+        rt: &Handle,
+        worker: W,
+        ctx: Context,
+        ctrl_rx: SmallReceiver<CtrlSignal>,
+        idle_timeout: Option<Duration>,
+    ) {
+        let relay = WorkerRelay::<W, M>::new(worker, ctx, idle_timeout);
         rt.spawn(relay.run(ctrl_rx));
     }
 }
@@ -1,5 +1,5 @@
 use crate::compat::futures::FutureExt;
-use crate::{Context, NodeBuilder};
+use crate::{Context, NodeBuilder, WorkerBuilder};
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::time::Duration;
 use ockam_core::compat::{
@@ -107,6 +107,51 @@ async fn simple_worker__run_node_lifecycle__worker_lifecycle_should_be_full(
     Ok(())
 }
 
+#[allow(non_snake_case)]
+#[ockam_macros::test(crate = "crate")]
+async fn simple_worker__idle_timeout__should_stop_after_inactivity(
+    ctx: &mut Context,
+) -> Result<()> {
+    let initialize_was_called = Arc::new(AtomicBool::new(false));
+    let shutdown_was_called = Arc::new(AtomicBool::new(false));
+
+    let worker = SimpleWorker {
+        initialize_was_called: initialize_was_called.clone(),
+        shutdown_was_called: shutdown_was_called.clone(),
+    };
+
+    WorkerBuilder::without_access_control("idle_worker", worker)
+        .idle_timeout(Duration::from_millis(200))
+        .start(ctx)
+        .await?;
+
+    // Each message resets the idle timer
+    for _ in 0..3 {
+        sleep(Duration::from_millis(100)).await;
+        ctx.send(route!["idle_worker"], "Hello".to_string()).await?;
+        let msg = ctx.receive::<String>().await?.take().body();
+        assert_eq!(msg, "Hello");
+    }
+    assert!(!shutdown_was_called.load(Ordering::Relaxed));
+
+    // Once idle, the worker goes away by itself
+    let idle_worker: Address = "idle_worker".into();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !shutdown_was_called.load(Ordering::Relaxed)
+            || ctx.list_workers().await?.contains(&idle_worker)
+        {
+            sleep(Duration::from_millis(10)).await;
+        }
+        Result::Ok(())
+    })
+    .await
+    .expect("idle worker should have stopped")?;
+
+    assert!(initialize_was_called.load(Ordering::Relaxed));
+
+    ctx.stop().await
+}
+
 struct DummyProcessor;
 
 #[async_trait]
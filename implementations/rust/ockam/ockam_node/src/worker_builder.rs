@@ -1,6 +1,7 @@
 use crate::debugger;
 use crate::error::{NodeError, NodeReason};
 use crate::{relay::WorkerRelay, Context, NodeMessage};
+use core::time::Duration;
 use ockam_core::compat::sync::Arc;
 use ockam_core::{
     errcode::{Kind, Origin},
@@ -21,6 +22,7 @@ use ockam_core::{
 pub struct WorkerBuilder<W> {
     mailboxes: Mailboxes,
     worker: W,
+    idle_timeout: Option<Duration>,
 }
 
 impl<M, W> WorkerBuilder<W>
@@ -33,7 +35,11 @@ where
         // TODO: @ac default to DenyAll
         let mailboxes = Mailboxes::main(address.into(), Arc::new(AllowAll), Arc::new(AllowAll));
 
-        Self { mailboxes, worker }
+        Self {
+            mailboxes,
+            worker,
+            idle_timeout: None,
+        }
     }
 
     /// Create a worker which inherits access control from the given context
@@ -67,7 +73,11 @@ where
 
         let mailboxes = Mailboxes::main(address, incoming_access_control, outgoing_access_control);
 
-        Self { mailboxes, worker }
+        Self {
+            mailboxes,
+            worker,
+            idle_timeout: None,
+        }
     }
 
     /// Create a worker which uses the given access control
@@ -83,13 +93,30 @@ where
             outgoing_access_control,
         );
 
-        Self { mailboxes, worker }
+        Self {
+            mailboxes,
+            worker,
+            idle_timeout: None,
+        }
     }
 
     /// Create a worker which uses the access control from the given
     /// [`Mailboxes`]
     pub fn with_mailboxes(mailboxes: Mailboxes, worker: W) -> Self {
-        Self { mailboxes, worker }
+        Self {
+            mailboxes,
+            worker,
+            idle_timeout: None,
+        }
+    }
+
+    /// Stop the worker once it hasn't received a message for `timeout`
+    ///
+    /// The worker is stopped like with [`Context::stop_worker()`], so
+    /// its [`Worker::shutdown`] hook runs. Disabled by default.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
     }
 
     /// Consume this builder and start a new Ockam [`Worker`] from the given context
@@ -117,7 +144,13 @@ where
         debugger::log_inherit_context("WORKER", context, &ctx);
 
         // Then initialise the worker message relay
-        WorkerRelay::<W, M>::init(context.runtime(), self.worker, ctx, ctrl_rx);
+        WorkerRelay::<W, M>::init(
+            context.runtime(),
+            self.worker,
+            ctx,
+            ctrl_rx,
+            self.idle_timeout,
+        );
 
         // Send start request to router
         let (msg, mut rx) =
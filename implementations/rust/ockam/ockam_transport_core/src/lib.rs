@@ -1,5 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub use error::TransportError;
+pub use reconnect::*;
 
 mod error;
+mod reconnect;
@@ -0,0 +1,160 @@
+use core::fmt::Debug;
+use core::time::Duration;
+use ockam_core::compat::rand::{thread_rng, Rng};
+
+/// Decides whether, and after how long, a lost connection is retried
+///
+/// Strategies are stateless: the caller counts its own attempts and
+/// starts again from `1` once a connection has been re-established.
+pub trait ReconnectStrategy: Debug + Send + Sync + 'static {
+    /// Delay before reconnection attempt number `attempt` (starting
+    /// at `1`), or `None` to give up
+    fn next_delay(&self, attempt: u32) -> Option<Duration>;
+}
+
+/// Never reconnect
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoReconnect;
+
+impl ReconnectStrategy for NoReconnect {
+    fn next_delay(&self, _attempt: u32) -> Option<Duration> {
+        None
+    }
+}
+
+/// Reconnect up to `max_attempts` times, waiting `delay` before each attempt
+#[derive(Clone, Copy, Debug)]
+pub struct FixedReconnect {
+    delay: Duration,
+    max_attempts: u32,
+}
+
+impl FixedReconnect {
+    /// Constructor
+    pub fn new(delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            delay,
+            max_attempts,
+        }
+    }
+}
+
+impl ReconnectStrategy for FixedReconnect {
+    fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+        Some(self.delay)
+    }
+}
+
+/// Reconnect up to `max_attempts` times, doubling the delay after each
+/// attempt up to `max_delay`
+///
+/// With jitter enabled, each delay is picked uniformly in
+/// `[delay / 2, delay]` so that many peers losing the same connection
+/// do not all retry at once.
+#[derive(Clone, Copy, Debug)]
+pub struct ExponentialReconnect {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: u32,
+    jitter: bool,
+}
+
+impl ExponentialReconnect {
+    /// Constructor, jitter is enabled by default
+    pub fn new(initial_delay: Duration, max_delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            initial_delay,
+            max_delay: max_delay.max(initial_delay),
+            max_attempts,
+            jitter: true,
+        }
+    }
+
+    /// Enable or disable jitter
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt - 1);
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |d| d.min(self.max_delay))
+    }
+}
+
+impl ReconnectStrategy for ExponentialReconnect {
+    fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+
+        let delay = self.base_delay(attempt);
+        if !self.jitter {
+            return Some(delay);
+        }
+
+        let half = delay / 2;
+        let spread = (delay - half).as_millis() as u64;
+        let jitter = Duration::from_millis(thread_rng().gen_range(0..=spread));
+        Some(half + jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_reconnect_gives_up() {
+        assert_eq!(NoReconnect.next_delay(1), None);
+    }
+
+    #[test]
+    fn fixed_reconnect_stops_after_max_attempts() {
+        let strategy = FixedReconnect::new(Duration::from_millis(100), 3);
+        for attempt in 1..=3 {
+            assert_eq!(
+                strategy.next_delay(attempt),
+                Some(Duration::from_millis(100))
+            );
+        }
+        assert_eq!(strategy.next_delay(4), None);
+    }
+
+    #[test]
+    fn exponential_reconnect_doubles_up_to_max_delay() {
+        let strategy =
+            ExponentialReconnect::new(Duration::from_millis(100), Duration::from_millis(500), 5)
+                .jitter(false);
+        let delays: Vec<_> = (1..=6).map(|a| strategy.next_delay(a)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                Some(Duration::from_millis(500)),
+                Some(Duration::from_millis(500)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn exponential_reconnect_jitter_stays_in_bounds() {
+        let strategy =
+            ExponentialReconnect::new(Duration::from_millis(100), Duration::from_secs(10), 8);
+        for attempt in 1..=8 {
+            let base = strategy.base_delay(attempt);
+            for _ in 0..20 {
+                let delay = strategy.next_delay(attempt).unwrap();
+                assert!(delay >= base / 2 && delay <= base);
+            }
+        }
+    }
+}
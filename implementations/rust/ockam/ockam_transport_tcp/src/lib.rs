@@ -25,6 +25,7 @@ extern crate core;
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+mod local_info;
 mod portal;
 mod router;
 mod workers;
@@ -35,6 +36,10 @@ pub(crate) use workers::*;
 
 mod transport;
 
+pub use local_info::*;
+pub use portal::{
+    HealthCheckOptions, KeepaliveOptions, OutletHealthStatus, OutletTraffic, Socks5Proxy,
+};
 pub use transport::*;
 
 use ockam_core::compat::net::SocketAddr;
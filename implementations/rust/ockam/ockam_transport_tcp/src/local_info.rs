@@ -0,0 +1,67 @@
+use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Decodable, Encodable, Error, LocalInfo, LocalMessage, Result};
+use serde::{Deserialize, Serialize};
+
+/// TCP LocalInfo unique Identifier
+pub const TCP_LOCAL_INFO_IDENTIFIER: &str = "TCP_LOCAL_INFO_IDENTIFIER";
+
+/// Connection metadata attached to every LocalMessage received over TCP
+///
+/// Messages that did not arrive over a TCP connection carry no such
+/// LocalInfo, so [`TcpLocalInfo::find_info`] returns an error for them.
+#[derive(Serialize, Deserialize)]
+pub struct TcpLocalInfo {
+    peer_address: String,
+}
+
+impl TcpLocalInfo {
+    /// Convert from LocalInfo
+    pub fn from_local_info(value: &LocalInfo) -> Result<Self> {
+        if value.type_identifier() != TCP_LOCAL_INFO_IDENTIFIER {
+            return Err(Error::new_without_cause(Origin::Transport, Kind::Invalid));
+        }
+
+        if let Ok(info) = TcpLocalInfo::decode(value.data()) {
+            return Ok(info);
+        }
+
+        Err(Error::new_without_cause(Origin::Transport, Kind::Invalid))
+    }
+
+    /// Convert to LocalInfo
+    pub fn to_local_info(&self) -> Result<LocalInfo> {
+        Ok(LocalInfo::new(
+            TCP_LOCAL_INFO_IDENTIFIER.into(),
+            self.encode()?,
+        ))
+    }
+
+    /// Find first such instance in LocalMessage
+    pub fn find_info(local_msg: &LocalMessage) -> Result<Self> {
+        if let Some(local_info) = local_msg
+            .local_info()
+            .iter()
+            .find(|x| x.type_identifier() == TCP_LOCAL_INFO_IDENTIFIER)
+        {
+            Self::from_local_info(local_info)
+        } else {
+            Err(Error::new_without_cause(Origin::Transport, Kind::Invalid))
+        }
+    }
+}
+
+impl TcpLocalInfo {
+    /// Constructor
+    pub fn new(peer_address: SocketAddr) -> Self {
+        Self {
+            peer_address: peer_address.to_string(),
+        }
+    }
+
+    /// Socket address of the remote end of the connection
+    pub fn peer_address(&self) -> Result<SocketAddr> {
+        crate::parse_socket_addr(&self.peer_address)
+    }
+}
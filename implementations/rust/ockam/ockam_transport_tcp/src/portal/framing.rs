@@ -0,0 +1,60 @@
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+use ockam_transport_core::TransportError;
+
+/// Size of the big-endian length that precedes every frame
+const FRAME_HEADER_SIZE: usize = 2;
+
+/// Prefix `payload` with its length, as in DNS over TCP
+pub(crate) fn encode_frame(payload: &[u8]) -> Result<Vec<u8>> {
+    let len = u16::try_from(payload.len()).map_err(|_| TransportError::Encoding)?;
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Remove every complete frame from the front of `buf` and return their
+/// payloads, a trailing partial frame stays in `buf`
+pub(crate) fn split_frames(buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while let Some(header) = buf.get(start..start + FRAME_HEADER_SIZE) {
+        let len = usize::from(u16::from_be_bytes([header[0], header[1]]));
+        let end = start + FRAME_HEADER_SIZE + len;
+        if end > buf.len() {
+            break;
+        }
+        frames.push(buf[start + FRAME_HEADER_SIZE..end].to_vec());
+        start = end;
+    }
+    buf.drain(..start);
+    frames
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_frame, split_frames};
+
+    #[test]
+    fn split_frames_keeps_partial_frame() {
+        let mut buf = encode_frame(b"hello").unwrap();
+        buf.extend(encode_frame(b"").unwrap());
+        buf.extend(encode_frame(b"world").unwrap());
+        buf.truncate(buf.len() - 2);
+
+        let frames = split_frames(&mut buf);
+        assert_eq!(vec![b"hello".to_vec(), vec![]], frames);
+        assert_eq!(vec![0, 5, b'w', b'o', b'r'], buf);
+
+        buf.extend(b"ld");
+        assert_eq!(vec![b"world".to_vec()], split_frames(&mut buf));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn encode_frame_rejects_oversized_payload() {
+        assert!(encode_frame(&[0; u16::MAX as usize]).is_ok());
+        assert!(encode_frame(&[0; u16::MAX as usize + 1]).is_err());
+    }
+}
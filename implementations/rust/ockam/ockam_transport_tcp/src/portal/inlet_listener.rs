@@ -19,6 +19,7 @@ pub(crate) struct TcpInletListenProcessor {
     inner: TcpListener,
     outlet_listener_route: Route,
     access_control: Arc<dyn AccessControl>,
+    framed: bool,
     // router_address: Address, // TODO @ac for AccessControl // FIXME: Why this is needed?
 }
 
@@ -29,6 +30,7 @@ impl TcpInletListenProcessor {
         outlet_listener_route: Route,
         addr: SocketAddr,
         access_control: Arc<dyn AccessControl>,
+        framed: bool,
         // router_address: Address,
     ) -> Result<(Address, SocketAddr)> {
         let waddr = Address::random_tagged("TcpInletListenProcessor");
@@ -46,6 +48,7 @@ impl TcpInletListenProcessor {
             inner,
             outlet_listener_route,
             access_control: access_control.clone(),
+            framed,
             // router_address,
         };
 
@@ -78,6 +81,7 @@ impl Processor for TcpInletListenProcessor {
             // self.router_address.clone(),
             self.outlet_listener_route.clone(),
             self.access_control.clone(),
+            self.framed,
         )
         .await?;
 
@@ -0,0 +1,82 @@
+use core::time::Duration;
+use ockam_core::Result;
+use ockam_transport_core::TransportError;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::TcpStream;
+
+/// TCP keepalive settings for the connections an Outlet opens to its peer
+///
+/// Keepalive probes stop NAT gateways and firewalls from dropping a
+/// portal connection that is idle for a long time.
+#[derive(Clone, Debug)]
+pub struct KeepaliveOptions {
+    time: Duration,
+    interval: Duration,
+    retries: u32,
+}
+
+impl KeepaliveOptions {
+    /// Constructor
+    ///
+    /// The first probe is sent after the connection has been idle for
+    /// `time`, then every `interval` until `retries` probes went
+    /// unanswered and the connection is considered dead.
+    pub fn new(time: Duration, interval: Duration, retries: u32) -> Self {
+        Self {
+            time,
+            interval,
+            retries,
+        }
+    }
+
+    /// Idle time before the first probe
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Time between two probes
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Number of unanswered probes after which the connection is dropped
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Enable keepalive on `stream`
+    pub(crate) fn apply(&self, stream: &TcpStream) -> Result<()> {
+        let keepalive = TcpKeepalive::new()
+            .with_time(self.time)
+            .with_retries(self.retries)
+            .with_interval(self.interval);
+        SockRef::from(stream)
+            .set_tcp_keepalive(&keepalive)
+            .map_err(TransportError::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KeepaliveOptions;
+    use core::time::Duration;
+    use socket2::SockRef;
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[tokio::test]
+    async fn apply_enables_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        assert!(!SockRef::from(&stream).keepalive().unwrap());
+
+        KeepaliveOptions::new(Duration::from_secs(60), Duration::from_secs(10), 3)
+            .apply(&stream)
+            .unwrap();
+
+        assert!(SockRef::from(&stream).keepalive().unwrap());
+    }
+}
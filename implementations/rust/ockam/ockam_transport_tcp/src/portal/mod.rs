@@ -1,11 +1,27 @@
+mod framing;
 mod inlet_listener;
+mod keepalive;
+mod outlet_health;
 mod outlet_listener;
+mod outlet_registry;
+mod outlet_traffic;
 mod portal_message;
 mod portal_receiver;
 mod portal_worker;
+mod relay;
+mod socks5;
+mod udp_outlet;
 
+pub(crate) use framing::*;
 pub(crate) use inlet_listener::*;
+pub(crate) use keepalive::*;
+pub(crate) use outlet_health::*;
 pub(crate) use outlet_listener::*;
+pub(crate) use outlet_registry::*;
+pub(crate) use outlet_traffic::*;
 pub(crate) use portal_message::*;
 pub(crate) use portal_receiver::*;
 pub(crate) use portal_worker::*;
+pub(crate) use relay::*;
+pub(crate) use socks5::*;
+pub(crate) use udp_outlet::*;
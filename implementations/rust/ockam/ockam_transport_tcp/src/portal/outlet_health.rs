@@ -0,0 +1,206 @@
+use crate::{IpFamilyPreference, Socks5Proxy, TcpRouterHandle};
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use core::time::Duration;
+use ockam_core::compat::{boxed::Box, sync::Arc};
+use ockam_core::{async_trait, Address, AllowAll, Mailbox, Mailboxes, Processor, Result};
+use ockam_node::{Context, ProcessorBuilder};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// Health check settings for an Outlet
+///
+/// The Outlet probes its peer with a short-lived TCP connection every
+/// `interval` and reports it as unhealthy after `failure_threshold`
+/// consecutive failed probes.
+#[derive(Clone, Debug)]
+pub struct HealthCheckOptions {
+    interval: Duration,
+    failure_threshold: u32,
+}
+
+impl HealthCheckOptions {
+    /// Constructor
+    pub fn new(interval: Duration, failure_threshold: u32) -> Self {
+        Self {
+            interval,
+            failure_threshold: failure_threshold.max(1),
+        }
+    }
+
+    /// Time between two probes
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Number of consecutive failed probes after which the peer is unhealthy
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+}
+
+/// Health of an Outlet's peer, as last seen by its health check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutletHealthStatus {
+    healthy: bool,
+    consecutive_failures: u32,
+    last_success: Option<SystemTime>,
+}
+
+impl OutletHealthStatus {
+    /// Whether the peer is currently considered reachable
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+
+    /// Number of failed probes since the last successful one
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// When the last successful probe happened, `None` if none has yet
+    pub fn last_success(&self) -> Option<SystemTime> {
+        self.last_success
+    }
+}
+
+/// Health state shared between an Outlet's health check and its readers
+#[derive(Debug)]
+pub(crate) struct OutletHealth {
+    options: HealthCheckOptions,
+    consecutive_failures: AtomicU32,
+    /// Milliseconds since the Unix epoch, 0 if no probe succeeded yet
+    last_success_ms: AtomicU64,
+}
+
+impl OutletHealth {
+    pub(crate) fn new(options: HealthCheckOptions) -> Self {
+        Self {
+            options,
+            consecutive_failures: AtomicU32::new(0),
+            last_success_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn options(&self) -> &HealthCheckOptions {
+        &self.options
+    }
+
+    /// Record the outcome of a probe and return the resulting status
+    pub(crate) fn record(&self, success: bool) -> OutletHealthStatus {
+        if success {
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default();
+            self.last_success_ms.store(now_ms.max(1), Ordering::Relaxed);
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+        } else {
+            let _ =
+                self.consecutive_failures
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                        Some(n.saturating_add(1))
+                    });
+        }
+        self.status()
+    }
+
+    pub(crate) fn status(&self) -> OutletHealthStatus {
+        let consecutive_failures = self.consecutive_failures.load(Ordering::Relaxed);
+        let last_success = match self.last_success_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(UNIX_EPOCH + Duration::from_millis(ms)),
+        };
+        OutletHealthStatus {
+            healthy: consecutive_failures < self.options.failure_threshold,
+            consecutive_failures,
+            last_success,
+        }
+    }
+}
+
+/// A TCP Portal Outlet health check processor
+///
+/// Started by `TcpTransport` for outlets created with
+/// [`OutletOptions::health_check`](crate::OutletOptions::health_check),
+/// and stopped together with the Outlet listener.
+pub(crate) struct TcpOutletHealthProcessor {
+    peer: String,
+    ip_family: IpFamilyPreference,
+    proxy: Option<Socks5Proxy>,
+    health: Arc<OutletHealth>,
+}
+
+impl TcpOutletHealthProcessor {
+    /// Start a new `TcpOutletHealthProcessor`
+    pub(crate) async fn start(
+        ctx: &Context,
+        peer: String,
+        ip_family: IpFamilyPreference,
+        proxy: Option<Socks5Proxy>,
+        health: Arc<OutletHealth>,
+    ) -> Result<Address> {
+        let address = Address::random_tagged("TcpOutletHealthProcessor");
+        let processor = Self {
+            peer,
+            ip_family,
+            proxy,
+            health,
+        };
+
+        let mailbox = Mailbox::new(address.clone(), Arc::new(AllowAll), Arc::new(AllowAll));
+        ProcessorBuilder::with_mailboxes(Mailboxes::new(mailbox, vec![]), processor)
+            .start(ctx)
+            .await?;
+
+        Ok(address)
+    }
+
+    async fn probe(&self) -> bool {
+        let interval = self.health.options().interval();
+        if let Some(proxy) = &self.proxy {
+            return matches!(
+                tokio::time::timeout(interval, proxy.connect(&self.peer)).await,
+                Ok(Ok(_))
+            );
+        }
+
+        let peer_addr = match TcpRouterHandle::resolve_peer_with(self.peer.clone(), self.ip_family)
+        {
+            Ok((peer_addr, _)) => peer_addr,
+            Err(err) => {
+                debug!(peer = %self.peer, %err, "outlet health check could not resolve peer");
+                return false;
+            }
+        };
+
+        matches!(
+            tokio::time::timeout(interval, TcpStream::connect(peer_addr)).await,
+            Ok(Ok(_))
+        )
+    }
+}
+
+#[async_trait]
+impl Processor for TcpOutletHealthProcessor {
+    type Context = Context;
+
+    async fn process(&mut self, ctx: &mut Self::Context) -> Result<bool> {
+        ctx.sleep(self.health.options().interval()).await;
+
+        let was_healthy = self.health.status().is_healthy();
+        let status = self.health.record(self.probe().await);
+
+        match (was_healthy, status.is_healthy()) {
+            (true, false) => warn!(
+                peer = %self.peer,
+                failures = %status.consecutive_failures(),
+                "outlet peer became unhealthy"
+            ),
+            (false, true) => info!(peer = %self.peer, "outlet peer is healthy again"),
+            _ => {}
+        }
+
+        Ok(true)
+    }
+}
@@ -1,9 +1,26 @@
-use crate::{PortalMessage, TcpPortalWorker, TcpRouterHandle};
-use ockam_core::{async_trait, AccessControl, Result, Routed, Worker};
+use crate::{
+    IpFamilyPreference, OutletWorkerOptions, PortalMessage, TcpOutletRegistry, TcpPortalWorker,
+    TcpRouterHandle, UdpPortalWorker, UDP_SCHEME,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::{async_trait, AccessControl, Address, Result, Routed, Worker};
 use ockam_node::Context;
-use ockam_transport_core::TransportError;
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// A portal started by an Outlet listener, counted until it is dropped
+///
+/// The listener takes the slot before it starts the portal's worker, so
+/// `Ping`s that arrive before the worker is initialized still see it.
+/// The worker holds on to the slot and frees it when it goes away,
+/// whether it shut down or never initialized.
+pub(crate) struct OutletSlot(Arc<AtomicUsize>);
+
+impl Drop for OutletSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 /// A TCP Portal Outlet listen worker
 ///
@@ -13,6 +30,13 @@ use tracing::debug;
 pub(crate) struct TcpOutletListenWorker {
     peer: String,
     access_control: Arc<dyn AccessControl>,
+    registry: TcpOutletRegistry,
+    health_processor: Option<Address>,
+    ip_family: IpFamilyPreference,
+    max_outlets: Option<usize>,
+    /// Number of portals started and not dropped yet
+    open_outlets: Arc<AtomicUsize>,
+    worker_options: OutletWorkerOptions,
     // router_address: Address, // TODO @ac for AccessControl // FIXME: Why is this needed
 }
 
@@ -21,14 +45,40 @@ impl TcpOutletListenWorker {
     pub(crate) fn new(
         peer: String,
         access_control: Arc<dyn AccessControl>,
+        registry: TcpOutletRegistry,
+        health_processor: Option<Address>,
+        ip_family: IpFamilyPreference,
+        max_outlets: Option<usize>,
+        worker_options: OutletWorkerOptions,
         // router_address: Address,
     ) -> Self {
         Self {
             peer,
             access_control,
+            registry,
+            health_processor,
+            ip_family,
+            max_outlets,
+            open_outlets: Arc::new(AtomicUsize::new(0)),
+            worker_options,
             // router_address,
         }
     }
+
+    /// Take a slot for a new portal, unless `max_outlets` are taken
+    fn take_slot(&self) -> Option<OutletSlot> {
+        let max_outlets = self.max_outlets.unwrap_or(usize::MAX);
+        self.open_outlets
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                if open < max_outlets {
+                    Some(open + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()?;
+        Some(OutletSlot(self.open_outlets.clone()))
+    }
 }
 
 #[async_trait]
@@ -36,6 +86,18 @@ impl Worker for TcpOutletListenWorker {
     type Context = Context;
     type Message = PortalMessage;
 
+    async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        self.registry.remove(&ctx.address());
+
+        if let Some(address) = self.health_processor.take() {
+            if let Err(err) = ctx.stop_processor(address).await {
+                debug!(%err, "outlet health check was already stopped");
+            }
+        }
+
+        Ok(())
+    }
+
     async fn handle_message(
         &mut self,
         ctx: &mut Self::Context,
@@ -43,19 +105,82 @@ impl Worker for TcpOutletListenWorker {
     ) -> Result<()> {
         let return_route = msg.return_route();
 
-        if let PortalMessage::Ping = msg.body() {
-        } else {
-            return Err(TransportError::Protocol.into());
+        match msg.body() {
+            PortalMessage::Ping => {}
+            other => {
+                warn!(
+                    %return_route,
+                    received = other.variant_name(),
+                    "outlet listener expected Ping, is the client skipping the handshake?"
+                );
+                return Err(other.unexpected_before_ping());
+            }
+        }
+
+        let slot = match self.take_slot() {
+            Some(slot) => slot,
+            None => {
+                warn!(
+                    peer = %self.peer,
+                    max_outlets = %self.max_outlets.unwrap_or_default(),
+                    "refusing new outlet, the listener is at capacity"
+                );
+                ctx.send(return_route, PortalMessage::Disconnect).await?;
+                return Ok(());
+            }
+        };
+
+        if let Some(health) = self.registry.health(&ctx.address()) {
+            if !health.is_healthy() {
+                warn!(
+                    peer = %self.peer,
+                    failures = %health.consecutive_failures(),
+                    "starting outlet while its peer is reported unhealthy"
+                );
+            }
+        }
+
+        let worker_options = OutletWorkerOptions {
+            sessions: self.registry.sessions(&ctx.address()),
+            slot: Some(Arc::new(slot)),
+            counters: self.registry.counters(&ctx.address()),
+            resolved_peer: self.registry.resolved_peer(&ctx.address()),
+            ..self.worker_options.clone()
+        };
+
+        if let Some(peer) = self.peer.strip_prefix(UDP_SCHEME) {
+            let (peer_addr, _) =
+                TcpRouterHandle::resolve_peer_with(peer.to_string(), self.ip_family)?;
+            let address = UdpPortalWorker::start_new_outlet(
+                ctx,
+                peer_addr,
+                return_route,
+                self.access_control.clone(),
+                worker_options,
+            )
+            .await?;
+
+            debug!("Created UDP Outlet at {}", &address);
+
+            return Ok(());
         }
 
-        let (peer_addr, _) = TcpRouterHandle::resolve_peer(self.peer.clone())?;
+        // Only the proxy may be able to resolve the peer
+        let peer = if self.worker_options.proxy.is_some() {
+            self.peer.clone()
+        } else {
+            let (peer_addr, _) =
+                TcpRouterHandle::resolve_peer_with(self.peer.clone(), self.ip_family)?;
+            peer_addr.to_string()
+        };
 
         let address = TcpPortalWorker::start_new_outlet(
             ctx,
-            peer_addr,
+            peer,
             // self.router_address.clone(),
             return_route.clone(),
             self.access_control.clone(),
+            worker_options,
         )
         .await?;
 
@@ -0,0 +1,128 @@
+use crate::{OutletCounters, OutletHealth, OutletHealthStatus, OutletTraffic};
+use ockam_core::compat::{net::SocketAddr, sync::Arc};
+use ockam_core::Address;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// State kept for an Outlet created through `TcpTransport`
+#[derive(Default)]
+pub(crate) struct OutletEntry {
+    pub(crate) health: Option<Arc<OutletHealth>>,
+    pub(crate) sessions: OutletSessions,
+    pub(crate) counters: Arc<OutletCounters>,
+    pub(crate) peer: ResolvedPeer,
+}
+
+/// The socket address the peer of an Outlet resolved to most recently
+///
+/// Cloning is cheap and all clones share the same address.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResolvedPeer(Arc<RwLock<Option<SocketAddr>>>);
+
+impl ResolvedPeer {
+    pub(crate) fn set(&self, peer: SocketAddr) {
+        *self.0.write().unwrap() = Some(peer);
+    }
+
+    pub(crate) fn get(&self) -> Option<SocketAddr> {
+        *self.0.read().unwrap()
+    }
+}
+
+/// The portal workers started by an Outlet listener that are still running
+///
+/// Maps each worker's internal address to the address of its receiver,
+/// if it has one.
+#[derive(Clone, Default)]
+pub(crate) struct OutletSessions(Arc<RwLock<BTreeMap<Address, Option<Address>>>>);
+
+impl OutletSessions {
+    pub(crate) fn insert(&self, internal_address: Address, receiver_address: Address) {
+        self.0
+            .write()
+            .unwrap()
+            .insert(internal_address, Some(receiver_address));
+    }
+
+    /// Register a worker that has no receiver, e.g. a portal relay
+    pub(crate) fn insert_worker(&self, internal_address: Address) {
+        self.0.write().unwrap().insert(internal_address, None);
+    }
+
+    pub(crate) fn remove(&self, internal_address: &Address) {
+        self.0.write().unwrap().remove(internal_address);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.read().unwrap().is_empty()
+    }
+
+    /// Return the `(internal, receiver)` addresses of all sessions
+    pub(crate) fn addresses(&self) -> Vec<(Address, Option<Address>)> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(i, r)| (i.clone(), r.clone()))
+            .collect()
+    }
+}
+
+/// The Outlets created by a `TcpTransport`, keyed by listener address
+///
+/// Cloning is cheap and all clones share the same entries.
+#[derive(Clone, Default)]
+pub(crate) struct TcpOutletRegistry {
+    outlets: Arc<RwLock<BTreeMap<Address, OutletEntry>>>,
+}
+
+impl TcpOutletRegistry {
+    pub(crate) fn insert(&self, address: Address, entry: OutletEntry) {
+        self.outlets.write().unwrap().insert(address, entry);
+    }
+
+    pub(crate) fn remove(&self, address: &Address) -> Option<OutletEntry> {
+        self.outlets.write().unwrap().remove(address)
+    }
+
+    pub(crate) fn health(&self, address: &Address) -> Option<OutletHealthStatus> {
+        self.outlets
+            .read()
+            .unwrap()
+            .get(address)
+            .and_then(|e| e.health.as_ref())
+            .map(|h| h.status())
+    }
+
+    pub(crate) fn counters(&self, address: &Address) -> Option<Arc<OutletCounters>> {
+        self.outlets
+            .read()
+            .unwrap()
+            .get(address)
+            .map(|e| e.counters.clone())
+    }
+
+    pub(crate) fn traffic(&self, address: &Address) -> Option<OutletTraffic> {
+        self.outlets
+            .read()
+            .unwrap()
+            .get(address)
+            .map(|e| e.counters.traffic())
+    }
+
+    pub(crate) fn resolved_peer(&self, address: &Address) -> Option<ResolvedPeer> {
+        self.outlets
+            .read()
+            .unwrap()
+            .get(address)
+            .map(|e| e.peer.clone())
+    }
+
+    pub(crate) fn sessions(&self, address: &Address) -> Option<OutletSessions> {
+        self.outlets
+            .read()
+            .unwrap()
+            .get(address)
+            .map(|e| e.sessions.clone())
+    }
+}
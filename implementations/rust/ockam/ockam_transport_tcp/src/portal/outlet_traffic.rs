@@ -0,0 +1,47 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of bytes that went through an Outlet, summed over all its
+/// connections to the peer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutletTraffic {
+    bytes_to_peer: u64,
+    bytes_from_peer: u64,
+}
+
+impl OutletTraffic {
+    /// Bytes written to the peer
+    pub fn bytes_to_peer(&self) -> u64 {
+        self.bytes_to_peer
+    }
+
+    /// Bytes read from the peer
+    pub fn bytes_from_peer(&self) -> u64 {
+        self.bytes_from_peer
+    }
+}
+
+/// Byte counters shared by all `TcpPortalWorker`s of an Outlet
+#[derive(Debug, Default)]
+pub(crate) struct OutletCounters {
+    bytes_to_peer: AtomicU64,
+    bytes_from_peer: AtomicU64,
+}
+
+impl OutletCounters {
+    pub(crate) fn add_to_peer(&self, bytes: usize) {
+        self.bytes_to_peer
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_from_peer(&self, bytes: usize) {
+        self.bytes_from_peer
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn traffic(&self) -> OutletTraffic {
+        OutletTraffic {
+            bytes_to_peer: self.bytes_to_peer.load(Ordering::Relaxed),
+            bytes_from_peer: self.bytes_from_peer.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use ockam_core::Message;
+use ockam_core::{Error, Message};
+use ockam_transport_core::TransportError;
 use serde::{Deserialize, Serialize};
 
 /// A command message type for a Portal
@@ -15,9 +16,49 @@ pub enum PortalMessage {
     Payload(Vec<u8>),
 }
 
+impl PortalMessage {
+    /// Name of the variant, for logs and errors
+    pub(crate) fn variant_name(&self) -> &'static str {
+        match self {
+            PortalMessage::Ping => "Ping",
+            PortalMessage::Pong => "Pong",
+            PortalMessage::Disconnect => "Disconnect",
+            PortalMessage::Payload(_) => "Payload",
+        }
+    }
+
+    /// Protocol error for a message that arrived when a `Ping` was expected
+    pub(crate) fn unexpected_before_ping(&self) -> Error {
+        Error::from(TransportError::Protocol)
+            .context("expected", "Ping")
+            .context("received", self.variant_name())
+    }
+}
+
 /// An internal message type for a Portal
-#[derive(Serialize, Deserialize, Message)]
+#[derive(Serialize, Deserialize, Message, Clone)]
 pub enum PortalInternalMessage {
-    /// Connection was dropped
+    /// Connection was closed by the other end
     Disconnect,
+    /// Reading from the connection failed
+    ReadFailed,
+    /// Time for the next attempt to re-establish a failed connection
+    Reconnect,
+    /// The Outlet this portal belongs to is shutting down
+    Shutdown,
+    /// Time to check whether any traffic went through the portal
+    IdleCheck,
+    /// Time to give up on a `Pong` answering our `Ping`
+    PongTimeout,
+}
+
+#[cfg(test)]
+mod test {
+    use super::PortalMessage;
+
+    #[test]
+    fn unexpected_before_ping_names_variant() {
+        let err = PortalMessage::Payload(vec![1, 2, 3]).unexpected_before_ping();
+        assert!(format!("{:?}", err).contains("Payload"));
+    }
 }
@@ -1,4 +1,6 @@
-use crate::{PortalInternalMessage, PortalMessage};
+use crate::{split_frames, OutletCounters, PortalInternalMessage, PortalMessage};
+use core::sync::atomic::{AtomicU64, Ordering};
+use ockam_core::compat::sync::Arc;
 use ockam_core::compat::vec::Vec;
 use ockam_core::{async_trait, Encodable, LocalMessage, Route, TransportMessage};
 use ockam_core::{route, Address, Processor, Result};
@@ -18,16 +20,38 @@ pub(crate) struct TcpPortalRecvProcessor {
     rx: OwnedReadHalf,
     sender_address: Address,
     onward_route: Route,
+    notify_remote: bool,
+    framed: bool,
+    counters: Option<Arc<OutletCounters>>,
+    activity: Arc<AtomicU64>,
 }
 
 impl TcpPortalRecvProcessor {
     /// Create a new `TcpPortalRecvProcessor`
-    pub fn new(rx: OwnedReadHalf, sender_address: Address, onward_route: Route) -> Self {
+    ///
+    /// If `notify_remote` is set, the other side of the portal is told
+    /// directly when the stream is closed. If `framed` is set, the stream
+    /// is split into length-prefixed frames and each one is sent as one
+    /// payload. Bytes read are added to `counters`, if any, and every
+    /// read bumps `activity`.
+    pub fn new(
+        rx: OwnedReadHalf,
+        sender_address: Address,
+        onward_route: Route,
+        notify_remote: bool,
+        framed: bool,
+        counters: Option<Arc<OutletCounters>>,
+        activity: Arc<AtomicU64>,
+    ) -> Self {
         Self {
             buf: Vec::with_capacity(MAX_PAYLOAD_SIZE),
             rx,
             sender_address,
             onward_route,
+            notify_remote,
+            framed,
+            counters,
+            activity,
         }
     }
 }
@@ -37,17 +61,33 @@ impl Processor for TcpPortalRecvProcessor {
     type Context = Context;
 
     async fn process(&mut self, ctx: &mut Context) -> Result<bool> {
-        self.buf.clear();
+        // A framed stream keeps the start of a partial frame
+        if !self.framed {
+            self.buf.clear();
+        }
 
-        let _len = match self.rx.read_buf(&mut self.buf).await {
+        let len = match self.rx.read_buf(&mut self.buf).await {
             Ok(len) => len,
             Err(err) => {
                 error!("Tcp Portal connection read failed with error: {}", err);
+                // The Sender decides whether to reconnect or to close the portal
+                if let Err(err) = ctx
+                    .send(
+                        route![self.sender_address.clone()],
+                        PortalInternalMessage::ReadFailed,
+                    )
+                    .await
+                {
+                    warn!(
+                        "Error notifying Tcp Portal Sender about failed connection {}",
+                        err
+                    );
+                }
                 return Ok(false);
             }
         };
 
-        if self.buf.is_empty() {
+        if len == 0 {
             // Notify Sender that connection was closed
             if let Err(err) = ctx
                 .send(
@@ -62,22 +102,37 @@ impl Processor for TcpPortalRecvProcessor {
                 );
             }
 
-            let msg = TransportMessage::v1(
-                self.onward_route.clone(),
-                self.sender_address.clone(),
-                PortalMessage::Disconnect.encode()?,
-            );
-            ctx.forward(LocalMessage::new(msg, vec![])).await?;
+            if self.notify_remote {
+                let msg = TransportMessage::v1(
+                    self.onward_route.clone(),
+                    self.sender_address.clone(),
+                    PortalMessage::Disconnect.encode()?,
+                );
+                ctx.forward(LocalMessage::new(msg, vec![])).await?;
+            }
 
             return Ok(false);
         }
 
-        // Loop just in case buf was extended (should not happen though)
-        for chunk in self.buf.chunks(MAX_PAYLOAD_SIZE) {
+        self.activity.fetch_add(1, Ordering::Relaxed);
+        if let Some(counters) = &self.counters {
+            counters.add_from_peer(len);
+        }
+
+        let payloads = if self.framed {
+            split_frames(&mut self.buf)
+        } else {
+            // Loop just in case buf was extended (should not happen though)
+            self.buf
+                .chunks(MAX_PAYLOAD_SIZE)
+                .map(|chunk| chunk.to_vec())
+                .collect()
+        };
+        for payload in payloads {
             let msg = TransportMessage::v1(
                 self.onward_route.clone(),
                 self.sender_address.clone(),
-                PortalMessage::Payload(chunk.to_vec()).encode()?,
+                PortalMessage::Payload(payload).encode()?,
             );
             ctx.forward(LocalMessage::new(msg, vec![])).await?;
         }
@@ -1,14 +1,19 @@
-use crate::{PortalInternalMessage, PortalMessage, TcpPortalRecvProcessor};
+use crate::{
+    encode_frame, KeepaliveOptions, OutletCounters, OutletSessions, OutletSlot,
+    PortalInternalMessage, PortalMessage, ResolvedPeer, Socks5Proxy, TcpPortalRecvProcessor,
+};
+use core::sync::atomic::{AtomicU64, Ordering};
 use core::time::Duration;
-use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc};
+use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc, vec::Vec};
+use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{async_trait, AccessControl, AllowAll, Decodable, Mailbox, Mailboxes};
-use ockam_core::{Address, Any, Result, Route, Routed, Worker};
-use ockam_node::{Context, ProcessorBuilder, WorkerBuilder};
-use ockam_transport_core::TransportError;
+use ockam_core::{Address, Any, Error, Result, Route, Routed, Worker};
+use ockam_node::{Context, DelayedEvent, ProcessorBuilder, WorkerBuilder};
+use ockam_transport_core::{ReconnectStrategy, TransportError};
 use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 /// Enumerate all `TcpPortalWorker` states
 ///
@@ -31,6 +36,27 @@ enum TypeName {
     Outlet,
 }
 
+/// Settings that only apply to `TcpPortalWorker`s of type [`TypeName::Outlet`]
+#[derive(Clone, Default)]
+pub(crate) struct OutletWorkerOptions {
+    /// Where the worker registers itself while it is running
+    pub(crate) sessions: Option<OutletSessions>,
+    /// Counts the worker against the listener's `max_outlets` while it exists
+    pub(crate) slot: Option<Arc<OutletSlot>>,
+    /// Where the worker counts the bytes it moves
+    pub(crate) counters: Option<Arc<OutletCounters>>,
+    /// Where the worker records the socket address of its peer
+    pub(crate) resolved_peer: Option<ResolvedPeer>,
+    /// How to re-establish the connection to the peer when it drops
+    pub(crate) reconnect: Option<Arc<dyn ReconnectStrategy>>,
+    /// TCP keepalive for the connection to the peer
+    pub(crate) keepalive: Option<KeepaliveOptions>,
+    /// Close the portal after this long without traffic
+    pub(crate) idle_timeout: Option<Duration>,
+    /// Connect to the peer through this proxy
+    pub(crate) proxy: Option<Socks5Proxy>,
+}
+
 /// A TCP Portal worker
 ///
 /// A TCP Portal worker is responsible for managing the life-cycle of
@@ -41,7 +67,8 @@ pub(crate) struct TcpPortalWorker {
     state: State,
     tx: Option<OwnedWriteHalf>,
     rx: Option<OwnedReadHalf>,
-    peer: SocketAddr,
+    /// `host:port` of the peer, only resolved by the proxy if there is one
+    peer: String,
     // router_address: Address, // TODO @ac for AccessControl
     internal_address: Address,
     remote_address: Address,
@@ -49,10 +76,32 @@ pub(crate) struct TcpPortalWorker {
     remote_route: Option<Route>,
     is_disconnecting: bool,
     type_name: TypeName,
+    /// Whether the stream carries length-prefixed frames
+    framed: bool,
+    sessions: Option<OutletSessions>,
+    _slot: Option<Arc<OutletSlot>>,
+    counters: Option<Arc<OutletCounters>>,
+    reconnect: Option<Arc<dyn ReconnectStrategy>>,
+    keepalive: Option<KeepaliveOptions>,
+    proxy: Option<Socks5Proxy>,
+    idle_timeout: Option<Duration>,
+    idle_check: Option<DelayedEvent<PortalInternalMessage>>,
+    /// Number of the next reconnection attempt while reconnecting
+    reconnect_attempt: Option<u32>,
+    reconnect_timer: Option<DelayedEvent<PortalInternalMessage>>,
+    /// Payloads from the other side waiting for the connection to be
+    /// re-established
+    pending: Vec<Vec<u8>>,
+    /// Bumped whenever bytes move in either direction
+    activity: Arc<AtomicU64>,
+    last_activity: u64,
 }
 
 impl TcpPortalWorker {
     /// Start a new `TcpPortalWorker` of type [`TypeName::Inlet`]
+    ///
+    /// If `framed` is set, the client delimits its messages with a
+    /// length prefix, see [`InletOptions::framed`](crate::InletOptions::framed).
     pub(crate) async fn start_new_inlet(
         ctx: &Context,
         stream: TcpStream,
@@ -60,27 +109,40 @@ impl TcpPortalWorker {
         // router_address: Address, // for AccessControl
         ping_route: Route,
         access_control: Arc<dyn AccessControl>,
+        framed: bool,
     ) -> Result<Address> {
         Self::start(
             ctx,
-            peer,
+            peer.to_string(),
             // router_address,
             State::SendPing { ping_route },
             Some(stream),
             TypeName::Inlet,
+            framed,
             access_control,
+            OutletWorkerOptions::default(),
         )
         .await
     }
 
     /// Start a new `TcpPortalWorker` of type [`TypeName::Outlet`]
+    ///
+    /// A `peer` that is a socket address, rather than a hostname left to
+    /// the proxy, is recorded in the options' `resolved_peer`.
     pub(crate) async fn start_new_outlet(
         ctx: &Context,
-        peer: SocketAddr,
+        peer: String,
         // router_address: Address, // for AccessControl
         pong_route: Route,
         access_control: Arc<dyn AccessControl>,
+        options: OutletWorkerOptions,
     ) -> Result<Address> {
+        if let (Some(resolved_peer), Ok(peer_addr)) =
+            (&options.resolved_peer, peer.parse::<SocketAddr>())
+        {
+            resolved_peer.set(peer_addr);
+        }
+
         Self::start(
             ctx,
             peer,
@@ -88,7 +150,9 @@ impl TcpPortalWorker {
             State::SendPong { pong_route },
             None,
             TypeName::Outlet,
+            false,
             access_control,
+            options,
         )
         .await
     }
@@ -96,12 +160,14 @@ impl TcpPortalWorker {
     /// Start a new `TcpPortalWorker`
     async fn start(
         ctx: &Context,
-        peer: SocketAddr,
+        peer: String,
         // router_address: Address,
         state: State,
         stream: Option<TcpStream>,
         type_name: TypeName,
+        framed: bool,
         access_control: Arc<dyn AccessControl>,
+        options: OutletWorkerOptions,
     ) -> Result<Address> {
         let internal_address = Address::random_tagged("TcpPortalWorker_internal");
         let remote_address = Address::random_tagged("TcpPortalWorker_remote");
@@ -132,6 +198,20 @@ impl TcpPortalWorker {
             receiver_address,
             is_disconnecting: false,
             type_name,
+            framed,
+            sessions: options.sessions,
+            _slot: options.slot,
+            counters: options.counters,
+            reconnect: options.reconnect,
+            keepalive: options.keepalive,
+            proxy: options.proxy,
+            idle_timeout: options.idle_timeout,
+            idle_check: None,
+            reconnect_attempt: None,
+            reconnect_timer: None,
+            pending: Vec::new(),
+            activity: Arc::new(AtomicU64::new(0)),
+            last_activity: 0,
         };
 
         // TODO: @ac 0#TcpPortalWorker_internal
@@ -187,6 +267,8 @@ enum DisconnectionReason {
     FailedTx,
     FailedRx,
     Remote,
+    Shutdown,
+    Idle,
 }
 
 impl TcpPortalWorker {
@@ -197,8 +279,17 @@ impl TcpPortalWorker {
     /// Start a `TcpPortalRecvProcessor`
     async fn start_receiver(&mut self, ctx: &Context, onward_route: Route) -> Result<()> {
         if let Some(rx) = self.rx.take() {
-            let receiver =
-                TcpPortalRecvProcessor::new(rx, self.internal_address.clone(), onward_route);
+            // With a reconnection strategy it is up to us, not the
+            // receiver, to tell the other side that the connection is gone
+            let receiver = TcpPortalRecvProcessor::new(
+                rx,
+                self.internal_address.clone(),
+                onward_route,
+                self.reconnect.is_none(),
+                self.framed,
+                self.counters.clone(),
+                self.activity.clone(),
+            );
 
             // TODO: @ac 0#TcpPortalRecvProcessor
             // in:  n/a
@@ -229,8 +320,8 @@ impl TcpPortalWorker {
         }
     }
 
-    async fn notify_remote_about_disconnection(&mut self, ctx: &Context) -> Result<()> {
-        // Notify the other end
+    /// Send `Disconnect` to the other side of the portal, at most once
+    async fn notify_remote(&mut self, ctx: &Context) -> Result<()> {
         if let Some(remote_route) = self.remote_route.take() {
             ctx.send_from_address(
                 remote_route,
@@ -245,6 +336,13 @@ impl TcpPortalWorker {
             );
         }
 
+        Ok(())
+    }
+
+    async fn notify_remote_about_disconnection(&mut self, ctx: &Context) -> Result<()> {
+        // Notify the other end
+        self.notify_remote(ctx).await?;
+
         // Avoiding race condition when both inlet and outlet connections
         // are dropped at the same time. In this case we want to wait for the `Disconnect`
         // message from the other side to reach our worker, before we shut it down which
@@ -277,6 +375,198 @@ impl TcpPortalWorker {
         Ok(())
     }
 
+    /// Close the portal if nothing moved since the previous check,
+    /// otherwise check again after another `idle_timeout`
+    async fn handle_idle_check(&mut self, ctx: &Context) -> Result<()> {
+        let activity = self.activity.load(Ordering::Relaxed);
+        if activity == self.last_activity {
+            return self
+                .start_disconnection(ctx, DisconnectionReason::Idle)
+                .await;
+        }
+        self.last_activity = activity;
+
+        if let (Some(timeout), Some(idle_check)) = (self.idle_timeout, &mut self.idle_check) {
+            idle_check.schedule(timeout).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Open the connection from an Outlet to its peer
+    async fn connect(&self) -> Result<TcpStream> {
+        let stream = match &self.proxy {
+            Some(proxy) => proxy.connect(&self.peer).await?,
+            None => TcpStream::connect(self.peer.as_str())
+                .await
+                .map_err(TransportError::from)?,
+        };
+        if let Some(keepalive) = &self.keepalive {
+            keepalive.apply(&stream)?;
+        }
+
+        Ok(stream)
+    }
+
+    /// Send a payload to the TCP stream, with a length prefix if the
+    /// stream is framed
+    ///
+    /// If the write fails and the connection can be re-established, the
+    /// payload is sent once more on the new connection. Payloads that
+    /// arrive while reconnecting are sent once reconnected.
+    async fn write_to_peer(&mut self, ctx: &Context, payload: Vec<u8>) -> Result<()> {
+        if self.reconnect_attempt.is_some() {
+            self.pending.push(payload);
+            return Ok(());
+        }
+
+        let tx = self.tx.as_mut().ok_or(TransportError::PortalInvalidState)?;
+        let written = if self.framed {
+            tx.write_all(&encode_frame(&payload)?).await
+        } else {
+            tx.write_all(&payload).await
+        };
+        match written {
+            Ok(()) => {
+                self.activity.fetch_add(1, Ordering::Relaxed);
+                if let Some(counters) = &self.counters {
+                    counters.add_to_peer(payload.len());
+                }
+                Ok(())
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to send message to peer {} with error: {}",
+                    self.peer, err
+                );
+                if self.start_reconnect(ctx).await? {
+                    self.pending.push(payload);
+                    Ok(())
+                } else {
+                    self.start_disconnection(ctx, DisconnectionReason::FailedTx)
+                        .await
+                }
+            }
+        }
+    }
+
+    /// Start re-establishing the connection to the peer of an Outlet
+    /// after an I/O error
+    ///
+    /// Returns `false` if there is no reconnection strategy or it doesn't
+    /// retry at all, in which case the portal must be disconnected.
+    async fn start_reconnect(&mut self, ctx: &Context) -> Result<bool> {
+        if self.reconnect_attempt.is_some() {
+            return Ok(true);
+        }
+        if self.reconnect.is_none() || self.remote_route.is_none() {
+            return Ok(false);
+        }
+
+        // The receiver may still be reading from the old stream
+        let _ = ctx.stop_processor(self.receiver_address.clone()).await;
+        self.tx = None;
+        self.rx = None;
+
+        self.reconnect_attempt = Some(1);
+        self.schedule_reconnect(ctx).await
+    }
+
+    /// Schedule the next reconnection attempt, unless the strategy
+    /// gives up
+    ///
+    /// The attempt is made when [`PortalInternalMessage::Reconnect`]
+    /// arrives, so the worker keeps handling other messages meanwhile.
+    async fn schedule_reconnect(&mut self, ctx: &Context) -> Result<bool> {
+        let attempt = self.reconnect_attempt.unwrap_or(1);
+        let delay = match self.reconnect.as_ref().and_then(|s| s.next_delay(attempt)) {
+            Some(delay) => delay,
+            None => return Ok(false),
+        };
+
+        if self.reconnect_timer.is_none() {
+            let timer = DelayedEvent::create(
+                ctx,
+                self.internal_address.clone(),
+                PortalInternalMessage::Reconnect,
+            )
+            .await?;
+            self.reconnect_timer = Some(timer);
+        }
+        if let Some(timer) = &mut self.reconnect_timer {
+            timer.schedule(delay).await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Make one attempt at re-establishing the connection to the peer
+    ///
+    /// Once the strategy gives up the portal is disconnected and an
+    /// error is returned.
+    async fn handle_reconnect(&mut self, ctx: &Context) -> Result<()> {
+        let attempt = match self.reconnect_attempt {
+            Some(attempt) => attempt,
+            None => return Ok(()),
+        };
+        let remote_route = self
+            .remote_route
+            .clone()
+            .ok_or(TransportError::PortalInvalidState)?;
+
+        let stream = match self.connect().await {
+            Ok(stream) => stream,
+            Err(err) => {
+                debug!(
+                    "Outlet at: {} failed to reconnect to peer {}: {}",
+                    self.internal_address, self.peer, err
+                );
+
+                self.reconnect_attempt = Some(attempt + 1);
+                if self.schedule_reconnect(ctx).await? {
+                    return Ok(());
+                }
+
+                error!(
+                    "Outlet at: {} gave up reconnecting to peer {} after {} attempts",
+                    self.internal_address, self.peer, attempt
+                );
+                self.start_disconnection(ctx, DisconnectionReason::FailedRx)
+                    .await?;
+                return Err(Error::new(
+                    Origin::Transport,
+                    Kind::Io,
+                    format!(
+                        "gave up reconnecting to peer {} after {} attempts",
+                        self.peer, attempt
+                    ),
+                ));
+            }
+        };
+
+        let (rx, tx) = stream.into_split();
+        self.tx = Some(tx);
+        self.rx = Some(rx);
+
+        self.receiver_address = Address::random_tagged("TcpPortalRecvProcessor");
+        if let Some(sessions) = &self.sessions {
+            sessions.insert(self.internal_address.clone(), self.receiver_address.clone());
+        }
+        self.start_receiver(ctx, remote_route).await?;
+        self.reconnect_attempt = None;
+
+        info!(
+            "Outlet at: {} reconnected to peer {} after {} attempts",
+            self.internal_address, self.peer, attempt
+        );
+
+        for payload in core::mem::take(&mut self.pending) {
+            self.write_to_peer(ctx, payload).await?;
+        }
+
+        Ok(())
+    }
+
     /// Start the portal disconnection process
     async fn start_disconnection(
         &mut self,
@@ -289,7 +579,9 @@ impl TcpPortalWorker {
             DisconnectionReason::FailedTx => {
                 self.notify_remote_about_disconnection(ctx).await?;
             }
-            DisconnectionReason::FailedRx => {
+            DisconnectionReason::FailedRx
+            | DisconnectionReason::Shutdown
+            | DisconnectionReason::Idle => {
                 self.notify_remote_about_disconnection(ctx).await?;
                 self.stop_receiver(ctx).await?;
             }
@@ -300,10 +592,22 @@ impl TcpPortalWorker {
 
         ctx.stop_worker(self.internal_address.clone()).await?;
 
-        info!(
-            "{:?} at: {} stopped due to connection drop",
-            self.type_name, self.internal_address
-        );
+        match reason {
+            DisconnectionReason::Shutdown => info!(
+                "{:?} at: {} stopped due to shutdown",
+                self.type_name, self.internal_address
+            ),
+            DisconnectionReason::Idle => info!(
+                "{:?} at: {} stopped after being idle for {:?}",
+                self.type_name,
+                self.internal_address,
+                self.idle_timeout.unwrap_or_default()
+            ),
+            _ => info!(
+                "{:?} at: {} stopped due to connection drop",
+                self.type_name, self.internal_address
+            ),
+        }
 
         Ok(())
     }
@@ -328,9 +632,7 @@ impl TcpPortalWorker {
         .await?;
 
         if self.tx.is_none() {
-            let stream = TcpStream::connect(self.peer)
-                .await
-                .map_err(TransportError::from)?;
+            let stream = self.connect().await?;
             let (rx, tx) = stream.into_split();
             self.tx = Some(tx);
             self.rx = Some(rx);
@@ -370,6 +672,52 @@ impl Worker for TcpPortalWorker {
             }
         }
 
+        if let Some(sessions) = &self.sessions {
+            sessions.insert(self.internal_address.clone(), self.receiver_address.clone());
+        }
+
+        if let Some(timeout) = self.idle_timeout {
+            let mut idle_check = DelayedEvent::create(
+                ctx,
+                self.internal_address.clone(),
+                PortalInternalMessage::IdleCheck,
+            )
+            .await?;
+            idle_check.schedule(timeout).await?;
+            self.idle_check = Some(idle_check);
+        }
+
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        if let Some(sessions) = &self.sessions {
+            sessions.remove(&self.internal_address);
+        }
+
+        // Stopped from the outside, e.g. because the node is stopping:
+        // close both ends cleanly instead of just dropping the stream
+        if !self.is_disconnecting {
+            self.is_disconnecting = true;
+
+            if let Some(mut tx) = self.tx.take() {
+                if let Err(err) = tx.flush().await {
+                    debug!(
+                        "{:?} at: {} failed to flush: {}",
+                        self.type_name, self.internal_address, err
+                    );
+                }
+                let _ = tx.shutdown().await;
+            }
+
+            if let Err(err) = self.notify_remote(ctx).await {
+                debug!(
+                    "{:?} at: {} could not notify the other side about shutdown: {}",
+                    self.type_name, self.internal_address, err
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -401,9 +749,18 @@ impl Worker for TcpPortalWorker {
 
                 let msg = PortalMessage::decode(msg.payload())?;
 
-                if let PortalMessage::Pong = msg {
-                } else {
-                    return Err(TransportError::Protocol.into());
+                match msg {
+                    PortalMessage::Pong => {}
+                    PortalMessage::Disconnect => {
+                        info!(
+                            "Inlet at: {} was refused by the Outlet",
+                            self.internal_address
+                        );
+                        return self
+                            .start_disconnection(ctx, DisconnectionReason::Remote)
+                            .await;
+                    }
+                    _ => return Err(TransportError::Protocol.into()),
                 }
 
                 self.start_receiver(ctx, return_route.clone()).await?;
@@ -426,12 +783,38 @@ impl Worker for TcpPortalWorker {
                     match msg {
                         PortalInternalMessage::Disconnect => {
                             info!(
-                                "Tcp stream was dropped for {:?} at: {}",
+                                "Tcp stream was closed for {:?} at: {}",
                                 self.type_name, self.internal_address
                             );
                             self.start_disconnection(ctx, DisconnectionReason::FailedRx)
                                 .await?;
                         }
+                        PortalInternalMessage::ReadFailed => {
+                            info!(
+                                "Tcp stream failed for {:?} at: {}",
+                                self.type_name, self.internal_address
+                            );
+                            if !self.start_reconnect(ctx).await? {
+                                self.start_disconnection(ctx, DisconnectionReason::FailedRx)
+                                    .await?;
+                            }
+                        }
+                        PortalInternalMessage::Reconnect => {
+                            self.handle_reconnect(ctx).await?;
+                        }
+                        PortalInternalMessage::Shutdown => {
+                            info!(
+                                "Shutting down {:?} at: {}",
+                                self.type_name, self.internal_address
+                            );
+                            self.start_disconnection(ctx, DisconnectionReason::Shutdown)
+                                .await?;
+                        }
+                        PortalInternalMessage::IdleCheck => {
+                            self.handle_idle_check(ctx).await?;
+                        }
+                        // Only portal relays wait for a `Pong` with a deadline
+                        PortalInternalMessage::PongTimeout => {}
                     }
                 } else {
                     trace!(
@@ -445,24 +828,7 @@ impl Worker for TcpPortalWorker {
 
                     match msg {
                         PortalMessage::Payload(payload) => {
-                            if let Some(tx) = &mut self.tx {
-                                match tx.write_all(&payload).await {
-                                    Ok(()) => {}
-                                    Err(err) => {
-                                        warn!(
-                                            "Failed to send message to peer {} with error: {}",
-                                            self.peer, err
-                                        );
-                                        self.start_disconnection(
-                                            ctx,
-                                            DisconnectionReason::FailedTx,
-                                        )
-                                        .await?;
-                                    }
-                                }
-                            } else {
-                                return Err(TransportError::PortalInvalidState.into());
-                            }
+                            self.write_to_peer(ctx, payload).await?;
                         }
                         PortalMessage::Disconnect => {
                             self.start_disconnection(ctx, DisconnectionReason::Remote)
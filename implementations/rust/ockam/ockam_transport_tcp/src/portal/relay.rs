@@ -0,0 +1,384 @@
+use crate::{
+    OutletSessions, PortalInternalMessage, PortalMessage, TcpOutletRegistry, RELAY_CONNECT_TIMEOUT,
+};
+use ockam_core::compat::{boxed::Box, sync::Arc};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{async_trait, AccessControl, AllowAll, Decodable, Mailbox, Mailboxes};
+use ockam_core::{Address, Any, Error, Result, Route, Routed, Worker};
+use ockam_node::{Context, DelayedEvent, WorkerBuilder};
+use ockam_transport_core::TransportError;
+use tracing::{debug, info, warn};
+
+/// A Portal relay listen worker
+///
+/// Portal relay listen workers are created by `TcpTransport` after a
+/// call is made to
+/// [`TcpTransport::create_portal_relay`](crate::TcpTransport::create_portal_relay).
+/// To an Inlet it looks like an Outlet: every `Ping` starts a new
+/// [`PortalRelayWorker`] that connects to the next Outlet. The relays
+/// are registered like the portals of an Outlet, so stopping the
+/// Outlet closes them.
+pub(crate) struct PortalRelayListenWorker {
+    outlet_route: Route,
+    access_control: Arc<dyn AccessControl>,
+    registry: TcpOutletRegistry,
+}
+
+impl PortalRelayListenWorker {
+    /// Create a new `PortalRelayListenWorker`
+    ///
+    /// `access_control` is checked for every message coming from an
+    /// Inlet, like for an Outlet.
+    pub(crate) fn new(
+        outlet_route: Route,
+        access_control: Arc<dyn AccessControl>,
+        registry: TcpOutletRegistry,
+    ) -> Self {
+        Self {
+            outlet_route,
+            access_control,
+            registry,
+        }
+    }
+}
+
+#[async_trait]
+impl Worker for PortalRelayListenWorker {
+    type Context = Context;
+    type Message = PortalMessage;
+
+    async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        self.registry.remove(&ctx.address());
+        Ok(())
+    }
+
+    async fn handle_message(
+        &mut self,
+        ctx: &mut Self::Context,
+        msg: Routed<Self::Message>,
+    ) -> Result<()> {
+        let return_route = msg.return_route();
+
+        match msg.body() {
+            PortalMessage::Ping => {}
+            other => {
+                warn!(
+                    %return_route,
+                    received = other.variant_name(),
+                    "portal relay expected Ping"
+                );
+                return Err(other.unexpected_before_ping());
+            }
+        }
+
+        let address = PortalRelayWorker::start(
+            ctx,
+            return_route,
+            self.outlet_route.clone(),
+            self.access_control.clone(),
+            self.registry.sessions(&ctx.address()),
+        )
+        .await?;
+
+        debug!("Created Portal relay at {}", &address);
+
+        Ok(())
+    }
+}
+
+/// Which leg of the relay a [`PortalRelayWorker`] shuts down for
+#[derive(Debug)]
+enum CloseReason {
+    /// The Inlet side sent `Disconnect`
+    InletDisconnected,
+    /// The Outlet side sent `Disconnect`
+    OutletDisconnected,
+    /// The Inlet side sent an invalid message or couldn't be reached
+    InletFailed(Error),
+    /// The Outlet side sent an invalid message, couldn't be reached or
+    /// didn't answer our `Ping` in time
+    OutletFailed(Error),
+    /// The relay listener is shutting down
+    Shutdown,
+}
+
+/// A Portal relay worker
+///
+/// Pumps [`PortalMessage`]s between an Inlet (the `inlet_address`
+/// side) and an Outlet (the `outlet_address` side), so that two
+/// portals can be chained without a TCP hop in between. A
+/// `Disconnect` from, or an error on, either side is forwarded to the
+/// other side as a `Disconnect` and stops the relay. So does an Outlet
+/// that doesn't answer our `Ping` within [`RELAY_CONNECT_TIMEOUT`].
+pub(crate) struct PortalRelayWorker {
+    internal_address: Address,
+    inlet_address: Address,
+    outlet_address: Address,
+    inlet_route: Route,
+    outlet_route: Route,
+    outlet_remote_route: Option<Route>,
+    /// The Inlet side closed before the Outlet answered our `Ping`
+    close_pending: bool,
+    sessions: Option<OutletSessions>,
+    pong_timeout: Option<DelayedEvent<PortalInternalMessage>>,
+}
+
+impl PortalRelayWorker {
+    /// Start a new `PortalRelayWorker`
+    async fn start(
+        ctx: &Context,
+        inlet_route: Route,
+        outlet_route: Route,
+        access_control: Arc<dyn AccessControl>,
+        sessions: Option<OutletSessions>,
+    ) -> Result<Address> {
+        let internal_address = Address::random_tagged("PortalRelayWorker_internal");
+        let inlet_address = Address::random_tagged("PortalRelayWorker_inlet");
+        let outlet_address = Address::random_tagged("PortalRelayWorker_outlet");
+
+        info!(
+            "Creating new Portal relay at inlet: {}, outlet: {}",
+            inlet_address, outlet_address
+        );
+
+        let worker = Self {
+            internal_address: internal_address.clone(),
+            inlet_address: inlet_address.clone(),
+            outlet_address: outlet_address.clone(),
+            inlet_route,
+            outlet_route,
+            outlet_remote_route: None,
+            close_pending: false,
+            sessions,
+            pong_timeout: None,
+        };
+
+        let mailboxes = Mailboxes::new(
+            Mailbox::new(internal_address, Arc::new(AllowAll), Arc::new(AllowAll)),
+            vec![
+                Mailbox::new(inlet_address.clone(), access_control, Arc::new(AllowAll)),
+                Mailbox::new(outlet_address, Arc::new(AllowAll), Arc::new(AllowAll)),
+            ],
+        );
+        WorkerBuilder::with_mailboxes(mailboxes, worker)
+            .start(ctx)
+            .await?;
+
+        Ok(inlet_address)
+    }
+
+    async fn close(&mut self, ctx: &Context, reason: CloseReason) -> Result<()> {
+        match &reason {
+            CloseReason::InletDisconnected | CloseReason::InletFailed(_) => {
+                match self.outlet_remote_route.take() {
+                    Some(route) => {
+                        Self::notify(ctx, route, self.outlet_address.clone()).await;
+                    }
+                    // The Outlet is told once it answers our `Ping`
+                    None if !self.close_pending => {
+                        self.close_pending = true;
+                        debug!(
+                            "Portal relay at: {} closes once it receives a pong: {:?}",
+                            self.inlet_address, reason
+                        );
+                        return Ok(());
+                    }
+                    None => {}
+                }
+            }
+            CloseReason::OutletDisconnected | CloseReason::OutletFailed(_) => {
+                Self::notify(ctx, self.inlet_route.clone(), self.inlet_address.clone()).await;
+            }
+            CloseReason::Shutdown => {
+                if let Some(route) = self.outlet_remote_route.take() {
+                    Self::notify(ctx, route, self.outlet_address.clone()).await;
+                }
+                Self::notify(ctx, self.inlet_route.clone(), self.inlet_address.clone()).await;
+            }
+        }
+
+        ctx.stop_worker(self.internal_address.clone()).await?;
+
+        info!(
+            "Portal relay at: {} stopped: {:?}",
+            self.inlet_address, reason
+        );
+
+        Ok(())
+    }
+
+    /// Send `Disconnect` to one side, which may already be gone
+    async fn notify(ctx: &Context, route: Route, from: Address) {
+        if let Err(err) = ctx
+            .send_from_address(route, PortalMessage::Disconnect, from)
+            .await
+        {
+            debug!("Portal relay could not send disconnect: {}", err);
+        }
+    }
+
+    async fn send_to_inlet(
+        &self,
+        ctx: &Context,
+        msg: PortalMessage,
+    ) -> core::result::Result<(), CloseReason> {
+        ctx.send_from_address(self.inlet_route.clone(), msg, self.inlet_address.clone())
+            .await
+            .map_err(CloseReason::InletFailed)
+    }
+
+    async fn send_to_outlet(
+        &self,
+        ctx: &Context,
+        msg: PortalMessage,
+    ) -> core::result::Result<(), CloseReason> {
+        let route = self
+            .outlet_remote_route
+            .clone()
+            .ok_or_else(|| CloseReason::InletFailed(TransportError::PortalInvalidState.into()))?;
+        ctx.send_from_address(route, msg, self.outlet_address.clone())
+            .await
+            .map_err(CloseReason::OutletFailed)
+    }
+
+    /// Handle a message from the Outlet side
+    async fn handle_outlet_message(
+        &mut self,
+        ctx: &Context,
+        return_route: Route,
+        payload: &[u8],
+    ) -> core::result::Result<(), CloseReason> {
+        match PortalMessage::decode(payload).map_err(CloseReason::OutletFailed)? {
+            PortalMessage::Pong => {
+                if self.outlet_remote_route.is_some() {
+                    return Err(CloseReason::OutletFailed(
+                        TransportError::PortalInvalidState.into(),
+                    ));
+                }
+                self.outlet_remote_route = Some(return_route);
+                if let Some(pong_timeout) = &mut self.pong_timeout {
+                    pong_timeout.cancel();
+                }
+                if self.close_pending {
+                    return Err(CloseReason::InletDisconnected);
+                }
+                self.send_to_inlet(ctx, PortalMessage::Pong).await?;
+
+                debug!("Portal relay at: {} received pong", self.inlet_address);
+            }
+            PortalMessage::Payload(payload) => {
+                self.send_to_inlet(ctx, PortalMessage::Payload(payload))
+                    .await?;
+            }
+            PortalMessage::Disconnect => return Err(CloseReason::OutletDisconnected),
+            PortalMessage::Ping => {
+                return Err(CloseReason::OutletFailed(TransportError::Protocol.into()))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a message from the relay itself
+    fn handle_internal_message(&self, payload: &[u8]) -> core::result::Result<(), CloseReason> {
+        match PortalInternalMessage::decode(payload).map_err(CloseReason::InletFailed)? {
+            PortalInternalMessage::Shutdown => Err(CloseReason::Shutdown),
+            PortalInternalMessage::PongTimeout if self.outlet_remote_route.is_none() => {
+                Err(CloseReason::OutletFailed(Error::new(
+                    Origin::Transport,
+                    Kind::Timeout,
+                    format!(
+                        "the Outlet did not answer within {:?}",
+                        RELAY_CONNECT_TIMEOUT
+                    ),
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Handle a message from the Inlet side
+    async fn handle_inlet_message(
+        &mut self,
+        ctx: &Context,
+        payload: &[u8],
+    ) -> core::result::Result<(), CloseReason> {
+        match PortalMessage::decode(payload).map_err(CloseReason::InletFailed)? {
+            PortalMessage::Payload(payload) => {
+                self.send_to_outlet(ctx, PortalMessage::Payload(payload))
+                    .await?;
+            }
+            PortalMessage::Disconnect => return Err(CloseReason::InletDisconnected),
+            PortalMessage::Ping | PortalMessage::Pong => {
+                return Err(CloseReason::InletFailed(TransportError::Protocol.into()))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Worker for PortalRelayWorker {
+    type Context = Context;
+    type Message = Any;
+
+    async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        // Force creation of the Outlet on the other side, the Inlet
+        // gets its `Pong` once the Outlet has answered ours
+        ctx.send_from_address(
+            self.outlet_route.clone(),
+            PortalMessage::Ping,
+            self.outlet_address.clone(),
+        )
+        .await?;
+
+        debug!("Portal relay at: {} sent ping", self.inlet_address);
+
+        let mut pong_timeout = DelayedEvent::create(
+            ctx,
+            self.internal_address.clone(),
+            PortalInternalMessage::PongTimeout,
+        )
+        .await?;
+        pong_timeout.schedule(RELAY_CONNECT_TIMEOUT).await?;
+        self.pong_timeout = Some(pong_timeout);
+
+        if let Some(sessions) = &self.sessions {
+            sessions.insert_worker(self.internal_address.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, _ctx: &mut Self::Context) -> Result<()> {
+        if let Some(sessions) = &self.sessions {
+            sessions.remove(&self.internal_address);
+        }
+
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let mut onward_route = msg.onward_route();
+        let recipient = onward_route.step()?;
+        let return_route = msg.return_route();
+
+        let res = if recipient == self.internal_address {
+            self.handle_internal_message(msg.payload())
+        } else if recipient == self.outlet_address {
+            self.handle_outlet_message(ctx, return_route, msg.payload())
+                .await
+        } else if self.close_pending {
+            // Only a `Pong` from the Outlet is still expected
+            Ok(())
+        } else {
+            self.handle_inlet_message(ctx, msg.payload()).await
+        };
+
+        match res {
+            Ok(()) => Ok(()),
+            Err(reason) => self.close(ctx, reason).await,
+        }
+    }
+}
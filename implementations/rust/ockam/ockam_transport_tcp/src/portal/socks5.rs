@@ -0,0 +1,186 @@
+use ockam_core::compat::net::{IpAddr, SocketAddr};
+use ockam_core::compat::{string::String, vec::Vec};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+use ockam_transport_core::TransportError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const USERNAME_PASSWORD_VERSION: u8 = 0x01;
+const CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// A SOCKS5 proxy an Outlet connects to its peer through
+///
+/// Supports the "no authentication" and "username/password" methods,
+/// see RFC 1928 and RFC 1929. Hostnames are sent to the proxy as is, so
+/// the proxy resolves them, not us.
+#[derive(Clone, Debug)]
+pub struct Socks5Proxy {
+    address: SocketAddr,
+    credentials: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    /// Constructor, for a proxy that requires no authentication
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            credentials: None,
+        }
+    }
+
+    /// Authenticate to the proxy with a username and password
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Address of the proxy
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Open a connection to `target`, a `host:port` string, through the proxy
+    pub(crate) async fn connect(&self, target: &str) -> Result<TcpStream> {
+        let mut stream = TcpStream::connect(self.address)
+            .await
+            .map_err(TransportError::from)?;
+
+        self.handshake(&mut stream, target).await.map_err(|err| {
+            warn!(proxy = %self.address, %target, %err, "SOCKS5 handshake failed");
+            err
+        })?;
+
+        debug!(proxy = %self.address, %target, "connected through SOCKS5 proxy");
+        Ok(stream)
+    }
+
+    async fn handshake(&self, stream: &mut TcpStream, target: &str) -> Result<()> {
+        let (host, port) = split_target(target)?;
+
+        // Method selection
+        let method = if self.credentials.is_some() {
+            USERNAME_PASSWORD
+        } else {
+            NO_AUTH
+        };
+        write(stream, &[VERSION, 1, method]).await?;
+        // The proxy answers `0xff` if it doesn't support ours
+        let [version, selected] = read::<2>(stream).await?;
+        if version != VERSION || selected != method {
+            return Err(TransportError::Protocol.into());
+        }
+
+        // Username/password sub-negotiation
+        if let Some((username, password)) = &self.credentials {
+            let (username, password) = (username.as_bytes(), password.as_bytes());
+            let (user_len, pass_len) =
+                match (u8::try_from(username.len()), u8::try_from(password.len())) {
+                    (Ok(u), Ok(p)) => (u, p),
+                    _ => return Err(TransportError::Encoding.into()),
+                };
+            let mut req = Vec::with_capacity(3 + username.len() + password.len());
+            req.push(USERNAME_PASSWORD_VERSION);
+            req.push(user_len);
+            req.extend_from_slice(username);
+            req.push(pass_len);
+            req.extend_from_slice(password);
+            write(stream, &req).await?;
+
+            let [_, status] = read::<2>(stream).await?;
+            if status != 0 {
+                return Err(Error::new(
+                    Origin::Authorization,
+                    Kind::Invalid,
+                    "SOCKS5 proxy rejected the credentials",
+                ));
+            }
+        }
+
+        // Connect request
+        let mut req = vec![VERSION, CONNECT, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                req.push(ATYP_IPV4);
+                req.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                req.push(ATYP_IPV6);
+                req.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                let len = u8::try_from(host.len()).map_err(|_| TransportError::Encoding)?;
+                req.push(ATYP_DOMAIN);
+                req.push(len);
+                req.extend_from_slice(host.as_bytes());
+            }
+        }
+        req.extend_from_slice(&port.to_be_bytes());
+        write(stream, &req).await?;
+
+        let [version, reply, _, atyp] = read::<4>(stream).await?;
+        if version != VERSION {
+            return Err(TransportError::Protocol.into());
+        }
+        if reply != 0 {
+            return Err(Error::new(
+                Origin::Transport,
+                Kind::NotFound,
+                format!("SOCKS5 proxy could not connect to {target}, reply {reply}"),
+            ));
+        }
+
+        // Skip the bound address and port
+        let len = match atyp {
+            ATYP_IPV4 => 4,
+            ATYP_IPV6 => 16,
+            ATYP_DOMAIN => usize::from(read::<1>(stream).await?[0]),
+            _ => return Err(TransportError::Protocol.into()),
+        };
+        let mut bound = vec![0; len + 2];
+        stream
+            .read_exact(&mut bound)
+            .await
+            .map_err(TransportError::from)?;
+
+        Ok(())
+    }
+}
+
+/// Split a `host:port` target, removing the brackets around an IPv6 host
+fn split_target(target: &str) -> Result<(&str, u16)> {
+    let (host, port) = target
+        .rsplit_once(':')
+        .ok_or(TransportError::InvalidAddress)?;
+    let port = port.parse().map_err(|_| TransportError::InvalidAddress)?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    Ok((host, port))
+}
+
+async fn write(stream: &mut TcpStream, buf: &[u8]) -> Result<()> {
+    stream.write_all(buf).await.map_err(TransportError::from)?;
+    Ok(())
+}
+
+async fn read<const N: usize>(stream: &mut TcpStream) -> Result<[u8; N]> {
+    let mut buf = [0; N];
+    stream
+        .read_exact(&mut buf)
+        .await
+        .map_err(TransportError::from)?;
+    Ok(buf)
+}
@@ -0,0 +1,309 @@
+use crate::{
+    OutletCounters, OutletSessions, OutletSlot, OutletWorkerOptions, PortalInternalMessage,
+    PortalMessage,
+};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc, vec::Vec};
+use ockam_core::Worker;
+use ockam_core::{async_trait, AccessControl, AllowAll, Decodable, Encodable, Mailbox, Mailboxes};
+use ockam_core::{Address, Any, LocalMessage, Processor, Result, Route, Routed, TransportMessage};
+use ockam_node::{Context, DelayedEvent, ProcessorBuilder, WorkerBuilder};
+use ockam_transport_core::TransportError;
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
+
+/// Scheme prefix of an Outlet peer that is reached over UDP
+pub(crate) const UDP_SCHEME: &str = "udp://";
+
+/// Largest payload of a UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// A UDP Portal Outlet worker
+///
+/// Started by the [`TcpOutletListenWorker`](crate::TcpOutletListenWorker)
+/// instead of a [`TcpPortalWorker`](crate::TcpPortalWorker) when the
+/// Outlet peer has the `udp://` scheme. Each `Payload` coming from the
+/// Inlet is sent to the peer as one datagram, and each datagram from the
+/// peer comes back to the Inlet as one `Payload`.
+///
+/// Message boundaries are preserved end to end if the Inlet is
+/// [framed](crate::InletOptions::framed): its client then sends and
+/// receives each datagram as one length-prefixed frame. A plain Inlet
+/// cuts its stream into `Payload`s wherever its reads happen to end.
+///
+/// UDP has no close, so the session lasts until the Inlet disconnects,
+/// the Outlet is stopped, or the `idle_timeout` of the Outlet passes
+/// without traffic. Reconnection, keepalive and proxy options don't
+/// apply.
+pub(crate) struct UdpPortalWorker {
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    internal_address: Address,
+    remote_address: Address,
+    receiver_address: Address,
+    remote_route: Option<Route>,
+    sessions: Option<OutletSessions>,
+    _slot: Option<Arc<OutletSlot>>,
+    counters: Option<Arc<OutletCounters>>,
+    idle_timeout: Option<Duration>,
+    idle_check: Option<DelayedEvent<PortalInternalMessage>>,
+    /// Bumped whenever a datagram moves in either direction
+    activity: Arc<AtomicU64>,
+    last_activity: u64,
+}
+
+impl UdpPortalWorker {
+    /// Start a new `UdpPortalWorker` sending datagrams to `peer`
+    pub(crate) async fn start_new_outlet(
+        ctx: &Context,
+        peer: SocketAddr,
+        pong_route: Route,
+        access_control: Arc<dyn AccessControl>,
+        options: OutletWorkerOptions,
+    ) -> Result<Address> {
+        let bind_addr: SocketAddr = if peer.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr)
+            .await
+            .map_err(TransportError::from)?;
+        socket.connect(peer).await.map_err(TransportError::from)?;
+        if let Some(resolved_peer) = &options.resolved_peer {
+            resolved_peer.set(peer);
+        }
+
+        let internal_address = Address::random_tagged("UdpPortalWorker_internal");
+        let remote_address = Address::random_tagged("UdpPortalWorker_remote");
+        let receiver_address = Address::random_tagged("UdpPortalRecvProcessor");
+
+        info!(
+            "Creating new UDP Outlet at internal: {}, remote: {}",
+            internal_address, remote_address
+        );
+
+        let worker = Self {
+            socket: Arc::new(socket),
+            peer,
+            internal_address: internal_address.clone(),
+            remote_address: remote_address.clone(),
+            receiver_address,
+            remote_route: Some(pong_route),
+            sessions: options.sessions,
+            _slot: options.slot,
+            counters: options.counters,
+            idle_timeout: options.idle_timeout,
+            idle_check: None,
+            activity: Arc::new(AtomicU64::new(0)),
+            last_activity: 0,
+        };
+
+        let mailboxes = Mailboxes::new(
+            Mailbox::new(internal_address, Arc::new(AllowAll), Arc::new(AllowAll)),
+            vec![Mailbox::new(
+                remote_address.clone(),
+                access_control,
+                Arc::new(AllowAll),
+            )],
+        );
+        WorkerBuilder::with_mailboxes(mailboxes, worker)
+            .start(ctx)
+            .await?;
+
+        Ok(remote_address)
+    }
+
+    async fn close(&mut self, ctx: &Context, notify_remote: bool) -> Result<()> {
+        if let Some(remote_route) = self.remote_route.take() {
+            if notify_remote {
+                ctx.send_from_address(
+                    remote_route,
+                    PortalMessage::Disconnect,
+                    self.remote_address.clone(),
+                )
+                .await?;
+            }
+        }
+
+        let _ = ctx.stop_processor(self.receiver_address.clone()).await;
+        ctx.stop_worker(self.internal_address.clone()).await?;
+
+        info!("UDP Outlet at: {} stopped", self.internal_address);
+
+        Ok(())
+    }
+
+    /// Close the session if no datagram moved since the previous check,
+    /// otherwise check again after another `idle_timeout`
+    async fn handle_idle_check(&mut self, ctx: &Context) -> Result<()> {
+        let activity = self.activity.load(Ordering::Relaxed);
+        if activity == self.last_activity {
+            info!(
+                "UDP Outlet at: {} is idle for {:?}",
+                self.internal_address,
+                self.idle_timeout.unwrap_or_default()
+            );
+            return self.close(ctx, true).await;
+        }
+        self.last_activity = activity;
+
+        if let (Some(timeout), Some(idle_check)) = (self.idle_timeout, &mut self.idle_check) {
+            idle_check.schedule(timeout).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Worker for UdpPortalWorker {
+    type Context = Context;
+    type Message = Any;
+
+    async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        let remote_route = self
+            .remote_route
+            .clone()
+            .ok_or(TransportError::PortalInvalidState)?;
+
+        ctx.send_from_address(
+            remote_route.clone(),
+            PortalMessage::Pong,
+            self.remote_address.clone(),
+        )
+        .await?;
+
+        let receiver = UdpPortalRecvProcessor {
+            socket: self.socket.clone(),
+            sender_address: self.remote_address.clone(),
+            onward_route: remote_route,
+            counters: self.counters.clone(),
+            activity: self.activity.clone(),
+            buf: vec![0; MAX_DATAGRAM_SIZE],
+        };
+        let mailbox = Mailbox::new(
+            self.receiver_address.clone(),
+            Arc::new(AllowAll),
+            Arc::new(AllowAll),
+        );
+        ProcessorBuilder::with_mailboxes(Mailboxes::new(mailbox, vec![]), receiver)
+            .start(ctx)
+            .await?;
+
+        if let Some(sessions) = &self.sessions {
+            sessions.insert(self.internal_address.clone(), self.receiver_address.clone());
+        }
+
+        if let Some(timeout) = self.idle_timeout {
+            let mut idle_check = DelayedEvent::create(
+                ctx,
+                self.internal_address.clone(),
+                PortalInternalMessage::IdleCheck,
+            )
+            .await?;
+            idle_check.schedule(timeout).await?;
+            self.idle_check = Some(idle_check);
+        }
+
+        debug!(
+            "UDP Outlet at: {} sending to peer {}",
+            self.internal_address, self.peer
+        );
+
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, _ctx: &mut Self::Context) -> Result<()> {
+        if let Some(sessions) = &self.sessions {
+            sessions.remove(&self.internal_address);
+        }
+
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        if self.remote_route.is_none() {
+            return Ok(());
+        }
+
+        let mut onward_route = msg.onward_route();
+        let recipient = onward_route.step()?;
+
+        if recipient == self.internal_address {
+            match PortalInternalMessage::decode(msg.payload())? {
+                PortalInternalMessage::Shutdown => self.close(ctx, true).await?,
+                PortalInternalMessage::IdleCheck => self.handle_idle_check(ctx).await?,
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match PortalMessage::decode(msg.payload())? {
+            PortalMessage::Payload(payload) => match self.socket.send(&payload).await {
+                Ok(len) => {
+                    self.activity.fetch_add(1, Ordering::Relaxed);
+                    if let Some(counters) = &self.counters {
+                        counters.add_to_peer(len);
+                    }
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to send datagram to peer {} with error: {}",
+                        self.peer, err
+                    );
+                }
+            },
+            PortalMessage::Disconnect => {
+                self.close(ctx, false).await?;
+            }
+            PortalMessage::Ping | PortalMessage::Pong => {
+                return Err(TransportError::Protocol.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Receives datagrams from the peer of a [`UdpPortalWorker`] and
+/// forwards them to the Inlet
+struct UdpPortalRecvProcessor {
+    socket: Arc<UdpSocket>,
+    sender_address: Address,
+    onward_route: Route,
+    counters: Option<Arc<OutletCounters>>,
+    activity: Arc<AtomicU64>,
+    buf: Vec<u8>,
+}
+
+#[async_trait]
+impl Processor for UdpPortalRecvProcessor {
+    type Context = Context;
+
+    async fn process(&mut self, ctx: &mut Context) -> Result<bool> {
+        let len = match self.socket.recv(&mut self.buf).await {
+            Ok(len) => len,
+            Err(err) => {
+                // E.g. an ICMP port unreachable for a previous datagram,
+                // the peer may still come up later
+                debug!("UDP Outlet failed to receive datagram: {}", err);
+                return Ok(true);
+            }
+        };
+        self.activity.fetch_add(1, Ordering::Relaxed);
+        if let Some(counters) = &self.counters {
+            counters.add_from_peer(len);
+        }
+
+        let msg = TransportMessage::v1(
+            self.onward_route.clone(),
+            self.sender_address.clone(),
+            PortalMessage::Payload(self.buf[..len].to_vec()).encode()?,
+        );
+        ctx.forward(LocalMessage::new(msg, vec![])).await?;
+
+        Ok(true)
+    }
+}
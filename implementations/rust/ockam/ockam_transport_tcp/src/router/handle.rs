@@ -1,7 +1,9 @@
 use crate::{
-    parse_socket_addr, TcpInletListenProcessor, TcpListenProcessor, TcpRouterRequest,
+    parse_socket_addr, IpFamilyPreference, ListenOptions, PortalInternalMessage,
+    TcpInletListenProcessor, TcpListenProcessor, TcpOutletRegistry, TcpRouterRequest,
     TcpRouterResponse, WorkerPair, TCP,
 };
+use core::time::Duration;
 use ockam_core::compat::net::{SocketAddr, ToSocketAddrs};
 use ockam_core::{
     async_trait,
@@ -19,6 +21,7 @@ pub(crate) struct TcpRouterHandle {
     ctx: Context,
     api_addr: Address,
     main_addr: Address,
+    outlets: TcpOutletRegistry,
 }
 
 #[async_trait]
@@ -41,17 +44,24 @@ impl AsyncTryClone for TcpRouterHandle {
             child_ctx,
             self.main_addr.clone(),
             self.api_addr.clone(),
+            self.outlets.clone(),
         ))
     }
 }
 
 impl TcpRouterHandle {
     /// Create a new `TcpRouterHandle` with the given address
-    pub(crate) fn new(ctx: Context, main_addr: Address, api_addr: Address) -> Self {
+    pub(crate) fn new(
+        ctx: Context,
+        main_addr: Address,
+        api_addr: Address,
+        outlets: TcpOutletRegistry,
+    ) -> Self {
         TcpRouterHandle {
             ctx,
             main_addr,
             api_addr,
+            outlets,
         }
     }
 
@@ -63,13 +73,28 @@ impl TcpRouterHandle {
     pub(crate) fn main_addr(&self) -> &Address {
         &self.main_addr
     }
+
+    /// Return the Outlets created through this router's transport
+    pub(crate) fn outlets(&self) -> &TcpOutletRegistry {
+        &self.outlets
+    }
 }
 
 impl TcpRouterHandle {
     /// Bind an incoming connection listener for this router
-    pub async fn bind(&self, addr: impl Into<SocketAddr>) -> Result<SocketAddr> {
+    pub async fn bind(
+        &self,
+        addr: impl Into<SocketAddr>,
+        options: ListenOptions,
+    ) -> Result<SocketAddr> {
         let socket_addr = addr.into();
-        TcpListenProcessor::start(&self.ctx, self.async_try_clone().await?, socket_addr).await
+        TcpListenProcessor::start(
+            &self.ctx,
+            self.async_try_clone().await?,
+            socket_addr,
+            options,
+        )
+        .await
     }
 
     /// Establish an outgoing TCP connection on an existing transport
@@ -172,6 +197,16 @@ impl TcpRouterHandle {
 
     /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr)
     pub(crate) fn resolve_peer(peer: impl Into<String>) -> Result<(SocketAddr, Vec<String>)> {
+        // FIXME: We only take ipv4 for now
+        Self::resolve_peer_with(peer, IpFamilyPreference::default())
+    }
+
+    /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr)
+    /// of the preferred IP family
+    pub(crate) fn resolve_peer_with(
+        peer: impl Into<String>,
+        family: IpFamilyPreference,
+    ) -> Result<(SocketAddr, Vec<String>)> {
         let peer_str = peer.into();
         let peer_addr;
         let hostnames;
@@ -182,9 +217,8 @@ impl TcpRouterHandle {
             hostnames = vec![];
         }
         // Try to resolve hostname
-        else if let Ok(mut iter) = peer_str.to_socket_addrs() {
-            // FIXME: We only take ipv4 for now
-            if let Some(p) = iter.find(|x| x.is_ipv4()) {
+        else if let Ok(iter) = peer_str.to_socket_addrs() {
+            if let Some(p) = family.select(iter) {
                 peer_addr = p;
             } else {
                 return Err(TransportError::InvalidAddress.into());
@@ -208,6 +242,7 @@ impl TcpRouterHandle {
         outlet_listener_route: impl Into<Route>,
         addr: impl Into<SocketAddr>,
         access_control: Arc<dyn AccessControl>,
+        framed: bool,
     ) -> Result<(Address, SocketAddr)> {
         let socket_addr = addr.into();
         TcpInletListenProcessor::start(
@@ -215,6 +250,7 @@ impl TcpRouterHandle {
             outlet_listener_route.into(),
             socket_addr,
             access_control,
+            framed,
             // self.main_addr.clone(),
         )
         .await
@@ -230,7 +266,94 @@ impl TcpRouterHandle {
 
     /// Stop the inlet's [`TcpPortalWorker`]
     pub async fn stop_outlet(&self, addr: impl Into<Address>) -> Result<()> {
+        let addr = addr.into();
+        self.outlets.remove(&addr);
         self.ctx.stop_worker(addr).await?;
         Ok(())
     }
+
+    /// Stop the outlet's listener and close all the [`TcpPortalWorker`]s it started
+    ///
+    /// Returns how many of them had to be stopped forcibly after `timeout`.
+    pub async fn shutdown_outlet(
+        &self,
+        addr: impl Into<Address>,
+        timeout: Duration,
+    ) -> Result<usize> {
+        let addr = addr.into();
+        let sessions = self.outlets.sessions(&addr);
+        self.stop_outlet(addr.clone()).await?;
+
+        let sessions = match sessions {
+            Some(sessions) => sessions,
+            None => return Ok(0),
+        };
+
+        for (internal_address, _) in sessions.addresses() {
+            if let Err(err) = self
+                .ctx
+                .send(internal_address.clone(), PortalInternalMessage::Shutdown)
+                .await
+            {
+                debug!(%internal_address, %err, "outlet session is already gone");
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !sessions.is_empty() && tokio::time::Instant::now() < deadline {
+            self.ctx.sleep(Duration::from_millis(50)).await;
+        }
+
+        let remaining = sessions.addresses();
+        for (internal_address, receiver_address) in &remaining {
+            sessions.remove(internal_address);
+            if let Some(receiver_address) = receiver_address {
+                let _ = self.ctx.stop_processor(receiver_address.clone()).await;
+            }
+            let _ = self.ctx.stop_worker(internal_address.clone()).await;
+        }
+
+        debug!(%addr, forced = %remaining.len(), "outlet shut down");
+
+        Ok(remaining.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{IpFamily, IpFamilyPreference, TcpRouterHandle};
+
+    #[test]
+    fn resolve_peer_keeps_literal_addresses() {
+        let (addr, _) = TcpRouterHandle::resolve_peer("[::1]:4000").unwrap();
+        assert!(addr.is_ipv6());
+
+        let strict_v6 = IpFamilyPreference::new(IpFamily::Ipv6).strict(true);
+        let (addr, _) = TcpRouterHandle::resolve_peer_with("127.0.0.1:4000", strict_v6).unwrap();
+        assert!(addr.is_ipv4());
+    }
+
+    #[test]
+    fn resolve_peer_with_family() {
+        let (addr, _) = TcpRouterHandle::resolve_peer("localhost:4000").unwrap();
+        assert!(addr.is_ipv4());
+
+        let (addr, _) = TcpRouterHandle::resolve_peer_with(
+            "localhost:4000",
+            IpFamilyPreference::new(IpFamily::Ipv4),
+        )
+        .unwrap();
+        assert!(addr.is_ipv4());
+
+        // On a dual-stack host `localhost` also resolves to `::1`,
+        // otherwise only the strict preference fails
+        let ipv6 = IpFamilyPreference::new(IpFamily::Ipv6);
+        match TcpRouterHandle::resolve_peer_with("localhost:4000", ipv6.strict(true)) {
+            Ok((addr, _)) => assert!(addr.is_ipv6()),
+            Err(_) => {
+                let (addr, _) = TcpRouterHandle::resolve_peer_with("localhost:4000", ipv6).unwrap();
+                assert!(addr.is_ipv4());
+            }
+        }
+    }
 }
@@ -1,4 +1,6 @@
-use crate::{TcpRouterHandle, TcpRouterRequest, TcpRouterResponse, TcpSendWorker, TCP};
+use crate::{
+    TcpOutletRegistry, TcpRouterHandle, TcpRouterRequest, TcpRouterResponse, TcpSendWorker, TCP,
+};
 use core::ops::Deref;
 use ockam_core::{async_trait, compat::sync::Arc, AllowAll};
 use ockam_core::{
@@ -23,6 +25,7 @@ pub(crate) struct TcpRouter {
     api_addr: Address,
     map: BTreeMap<Address, Address>,
     allow_auto_connection: bool,
+    outlets: TcpOutletRegistry,
 }
 
 impl TcpRouter {
@@ -59,6 +62,7 @@ impl TcpRouter {
             api_addr: api_addr.clone(),
             map: BTreeMap::new(),
             allow_auto_connection: true,
+            outlets: TcpOutletRegistry::default(),
         };
 
         let handle = router.create_self_handle().await?;
@@ -118,8 +122,12 @@ impl TcpRouter {
         );
         let handle_ctx = self.ctx.new_detached_with_mailboxes(mailboxes).await?;
 
-        let handle =
-            TcpRouterHandle::new(handle_ctx, self.main_addr.clone(), self.api_addr.clone());
+        let handle = TcpRouterHandle::new(
+            handle_ctx,
+            self.main_addr.clone(),
+            self.api_addr.clone(),
+            self.outlets.clone(),
+        );
         Ok(handle)
     }
 }
@@ -1,9 +1,19 @@
+use core::time::Duration;
 use ockam_core::access_control::AccessControl;
 use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc};
-use ockam_core::{Address, AllowAll, AsyncTryClone, Result, Route};
-use ockam_node::Context;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Address, AllowAll, AsyncTryClone, Error, Result, Route};
+use ockam_node::{Context, WorkerBuilder};
+use ockam_transport_core::{ReconnectStrategy, TransportError};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tracing::warn;
 
-use crate::{parse_socket_addr, TcpOutletListenWorker, TcpRouter, TcpRouterHandle};
+use crate::{
+    parse_socket_addr, HealthCheckOptions, KeepaliveOptions, OutletEntry, OutletHealth,
+    OutletHealthStatus, OutletTraffic, OutletWorkerOptions, PortalRelayListenWorker, ResolvedPeer,
+    Socks5Proxy, TcpOutletHealthProcessor, TcpOutletListenWorker, TcpRouter, TcpRouterHandle,
+    UDP_SCHEME,
+};
 
 /// High level management interface for TCP transports
 ///
@@ -107,16 +117,90 @@ impl TcpTransport {
     /// tcp.listen("127.0.0.1:8000").await?;
     /// # Ok(()) }
     pub async fn listen<S: AsRef<str>>(&self, bind_addr: S) -> Result<SocketAddr> {
+        self.listen_extended(bind_addr, ListenOptions::default())
+            .await
+    }
+
+    /// Start listening to incoming connections with the given socket options
+    ///
+    /// ```rust
+    /// use ockam_transport_tcp::{ListenOptions, TcpTransport};
+    /// # use ockam_node::Context;
+    /// # use ockam_core::Result;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let tcp = TcpTransport::create(&ctx).await?;
+    /// let options = ListenOptions::default().backlog(4096);
+    /// tcp.listen_extended("127.0.0.1:8000", options).await?;
+    /// # Ok(()) }
+    pub async fn listen_extended<S: AsRef<str>>(
+        &self,
+        bind_addr: S,
+        options: ListenOptions,
+    ) -> Result<SocketAddr> {
         let bind_addr = parse_socket_addr(bind_addr.as_ref())?;
-        self.router_handle.bind(bind_addr).await
+        self.router_handle.bind(bind_addr, options).await
+    }
+}
+
+/// Socket options for [`TcpTransport::listen_extended`]
+#[derive(Clone, Debug)]
+pub struct ListenOptions {
+    backlog: u32,
+    reuse_address: Option<bool>,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        Self {
+            backlog: 1024,
+            reuse_address: None,
+        }
     }
 }
 
+impl ListenOptions {
+    /// Maximum number of pending connections not yet accepted, 1024 by
+    /// default. The OS may cap it.
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Set `SO_REUSEADDR` on the listening socket. If this is not called
+    /// it matches tokio's `TcpListener::bind`: set on unix, unset elsewhere.
+    pub fn reuse_address(mut self, reuse_address: bool) -> Self {
+        self.reuse_address = Some(reuse_address);
+        self
+    }
+
+    /// Bind a listener to `addr` with these options
+    pub(crate) fn bind(&self, addr: SocketAddr) -> std::io::Result<TcpListener> {
+        let socket = if addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        let reuse_address = self.reuse_address.unwrap_or(cfg!(unix));
+        socket.set_reuseaddr(reuse_address)?;
+        socket.bind(addr)?;
+        socket.listen(self.backlog)
+    }
+}
+
+/// How long [`TcpTransport::stop_outlet`] waits for an outlet's
+/// connections to close before stopping them forcibly
+pub const STOP_OUTLET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a portal relay waits for the next Outlet to answer before
+/// it closes the Inlet side, see [`TcpTransport::create_portal_relay`]
+pub const RELAY_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Args to start an Inlet
 pub struct InletOptions {
     bind_addr: String,
     outlet_route: Route,
     access_control: Arc<dyn AccessControl>,
+    framed: bool,
 }
 
 impl InletOptions {
@@ -131,8 +215,108 @@ impl InletOptions {
             bind_addr,
             outlet_route,
             access_control,
+            framed: false,
         }
     }
+
+    /// Expect clients to send each message with a 2-byte big-endian
+    /// length prefix, as in DNS over TCP. Each message goes to the Outlet
+    /// as one payload, and each payload from the Outlet is written back
+    /// with its length prefix. This preserves the datagrams of a UDP
+    /// Outlet end to end.
+    pub fn framed(mut self, framed: bool) -> Self {
+        self.framed = framed;
+        self
+    }
+}
+
+/// An IP family
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpFamily {
+    /// IPv4
+    Ipv4,
+    /// IPv6
+    Ipv6,
+}
+
+impl IpFamily {
+    fn contains(&self, addr: &SocketAddr) -> bool {
+        match self {
+            Self::Ipv4 => addr.is_ipv4(),
+            Self::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+/// Which IP family an Outlet connects over when its peer hostname
+/// resolves to addresses of both families
+///
+/// Peers given as a literal socket address are used as is. By default
+/// the first IPv4 address a hostname resolves to is used, and resolution
+/// fails if there is none.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpFamilyPreference {
+    family: IpFamily,
+    strict: bool,
+}
+
+impl Default for IpFamilyPreference {
+    fn default() -> Self {
+        Self::new(IpFamily::Ipv4).strict(true)
+    }
+}
+
+impl IpFamilyPreference {
+    /// Prefer addresses of `family`, falling back to the other family
+    /// if the hostname has none
+    pub fn new(family: IpFamily) -> Self {
+        Self {
+            family,
+            strict: false,
+        }
+    }
+
+    /// Fail instead of falling back if the hostname has no address of
+    /// the preferred family
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Pick the best address among resolved `addrs`
+    pub(crate) fn select(&self, addrs: impl Iterator<Item = SocketAddr>) -> Option<SocketAddr> {
+        let (preferred, others): (Vec<_>, Vec<_>) = addrs.partition(|a| self.family.contains(a));
+        match preferred.first() {
+            Some(addr) => Some(*addr),
+            None if self.strict => None,
+            None => others.first().copied(),
+        }
+    }
+}
+
+/// An Outlet started by [`TcpTransport::create_outlet_extended`]
+#[derive(Clone, Debug)]
+pub struct OutletInfo {
+    address: Address,
+    peer: ResolvedPeer,
+}
+
+impl OutletInfo {
+    /// Address of the Outlet, to route Inlets to
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Socket address the peer resolved to most recently
+    ///
+    /// Set when the Outlet is created if the peer was resolved up front,
+    /// see [`OutletOptions::resolve_eagerly`] and
+    /// [`OutletOptions::validate`], and updated whenever a portal
+    /// connects. `None` until then, and for a hostname that is only
+    /// resolved by a SOCKS5 proxy.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.peer.get()
+    }
 }
 
 /// Args to start an Outlet
@@ -140,18 +324,113 @@ pub struct OutletOptions {
     address: Address,
     peer: String,
     access_control: Arc<dyn AccessControl>,
+    validate: bool,
+    resolve_eagerly: bool,
+    health_check: Option<HealthCheckOptions>,
+    reconnect: Option<Arc<dyn ReconnectStrategy>>,
+    ip_family: IpFamilyPreference,
+    keepalive: Option<KeepaliveOptions>,
+    idle_timeout: Option<Duration>,
+    max_outlets: Option<usize>,
+    proxy: Option<Socks5Proxy>,
 }
 
 impl OutletOptions {
     // TODO: Generics
     /// Constructor
+    ///
+    /// `access_control` is checked for every message coming from an
+    /// Inlet, including the `Ping` that opens a portal. Policies see
+    /// the requester through the message's return route and its
+    /// `LocalInfo`, e.g. [`TcpLocalInfo`](crate::TcpLocalInfo). To only
+    /// open portals for `Ping`s that come over a secure channel from
+    /// known identities, use ockam_identity's `IdentityIdAccessControl`.
     pub fn new(address: Address, peer: String, access_control: Arc<dyn AccessControl>) -> Self {
         Self {
             address,
             peer,
             access_control,
+            validate: false,
+            resolve_eagerly: false,
+            health_check: None,
+            reconnect: None,
+            ip_family: IpFamilyPreference::default(),
+            keepalive: None,
+            idle_timeout: None,
+            max_outlets: None,
+            proxy: None,
         }
     }
+
+    /// Resolve and try to connect to the peer when the Outlet is created
+    /// and fail if it is unreachable. The test connection is closed right
+    /// away. A UDP peer is only resolved.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Resolve the peer when the Outlet is created and fail right away,
+    /// without starting anything, if it can't be resolved. The peer is
+    /// still resolved again for every new portal.
+    pub fn resolve_eagerly(mut self, resolve_eagerly: bool) -> Self {
+        self.resolve_eagerly = resolve_eagerly;
+        self
+    }
+
+    /// Periodically probe the peer and track whether it is reachable,
+    /// see [`TcpTransport::outlet_health`]. UDP peers can't be probed,
+    /// creating their Outlet with a health check fails.
+    pub fn health_check(mut self, options: HealthCheckOptions) -> Self {
+        self.health_check = Some(options);
+        self
+    }
+
+    /// Re-establish a failed connection to the peer according to
+    /// `strategy` instead of closing the portal right away. The portal
+    /// is closed once the strategy gives up. A connection the peer
+    /// closes cleanly still closes the portal.
+    pub fn reconnect(mut self, strategy: impl ReconnectStrategy) -> Self {
+        self.reconnect = Some(Arc::new(strategy));
+        self
+    }
+
+    /// Which IP family to connect over when the peer is a hostname that
+    /// resolves to both IPv4 and IPv6 addresses, IPv4 only by default
+    pub fn ip_family(mut self, ip_family: IpFamilyPreference) -> Self {
+        self.ip_family = ip_family;
+        self
+    }
+
+    /// Enable TCP keepalive on the connections to the peer
+    pub fn keepalive(mut self, options: KeepaliveOptions) -> Self {
+        self.keepalive = Some(options);
+        self
+    }
+
+    /// Close a portal once no bytes went through it, in either
+    /// direction, for at least `timeout`.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Refuse new portals while `max_outlets` portals are already
+    /// connected through this Outlet.
+    pub fn max_outlets(mut self, max_outlets: usize) -> Self {
+        self.max_outlets = Some(max_outlets);
+        self
+    }
+
+    /// Connect to the peer through a SOCKS5 proxy instead of directly.
+    /// The peer is resolved by the proxy, never locally, so
+    /// [`OutletOptions::resolve_eagerly`] and
+    /// [`OutletOptions::ip_family`] have no effect. Validation and health
+    /// checks go through the proxy too. Ignored for UDP peers.
+    pub fn socks5_proxy(mut self, proxy: Socks5Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
 }
 
 impl TcpTransport {
@@ -162,7 +441,12 @@ impl TcpTransport {
     ) -> Result<(Address, SocketAddr)> {
         let bind_addr = parse_socket_addr(options.bind_addr)?;
         self.router_handle
-            .bind_inlet(options.outlet_route, bind_addr, options.access_control)
+            .bind_inlet(
+                options.outlet_route,
+                bind_addr,
+                options.access_control,
+                options.framed,
+            )
             .await
     }
 
@@ -216,14 +500,128 @@ impl TcpTransport {
     }
 
     /// Create an Outlet
-    pub async fn create_outlet_extended(&self, options: OutletOptions) -> Result<()> {
-        let worker = TcpOutletListenWorker::new(options.peer, options.access_control);
-        self.router_handle
-            .ctx()
-            .start_worker(options.address, worker)
-            .await?;
+    ///
+    /// The peer is resolved again for every new portal, so later portals
+    /// follow DNS changes. The returned [`OutletInfo`] tracks the latest
+    /// resolution.
+    pub async fn create_outlet_extended(&self, options: OutletOptions) -> Result<OutletInfo> {
+        let (udp, peer) = match options.peer.strip_prefix(UDP_SCHEME) {
+            Some(peer) => (true, peer.to_string()),
+            None => (false, options.peer.clone()),
+        };
+        // A probe is a TCP connection, which a UDP peer never accepts
+        if udp && options.health_check.is_some() {
+            return Err(Error::new(
+                Origin::Transport,
+                Kind::Unsupported,
+                "UDP outlet peers don't support health checks",
+            ));
+        }
+        let unreachable = |err: ockam_core::Error| {
+            warn!(peer = %options.peer, %err, "outlet peer is unreachable");
+            err
+        };
+        let mut resolved = None;
+        match &options.proxy {
+            // Only the proxy may be able to resolve the peer
+            Some(proxy) if !udp => {
+                if options.validate {
+                    drop(proxy.connect(&peer).await.map_err(unreachable)?);
+                }
+            }
+            _ => {
+                if options.validate || options.resolve_eagerly {
+                    let (peer_addr, _) =
+                        TcpRouterHandle::resolve_peer_with(peer.clone(), options.ip_family)?;
+                    resolved = Some(peer_addr);
+                }
 
-        Ok(())
+                // There is no connection to test for a UDP peer
+                if let Some(peer_addr) = resolved.filter(|_| options.validate && !udp) {
+                    let stream = TcpStream::connect(peer_addr)
+                        .await
+                        .map_err(|err| unreachable(TransportError::from(err).into()))?;
+                    drop(stream);
+                }
+            }
+        }
+
+        let ctx = self.router_handle.ctx();
+        let outlets = self.router_handle.outlets();
+
+        let mut entry = OutletEntry::default();
+        if let Some(peer_addr) = resolved {
+            entry.peer.set(peer_addr);
+        }
+        let info = OutletInfo {
+            address: options.address.clone(),
+            peer: entry.peer.clone(),
+        };
+        let mut health_processor = None;
+        if let Some(health_check) = options.health_check {
+            let health = Arc::new(OutletHealth::new(health_check));
+            health_processor = Some(
+                TcpOutletHealthProcessor::start(
+                    ctx,
+                    peer,
+                    options.ip_family,
+                    options.proxy.clone(),
+                    health.clone(),
+                )
+                .await?,
+            );
+            entry.health = Some(health);
+        }
+        outlets.insert(options.address.clone(), entry);
+
+        // The Inlet's `Ping` goes through the same policy as the traffic
+        // that follows, so a policy can refuse to open the portal at all
+        let access_control = options.access_control.clone();
+        let worker = TcpOutletListenWorker::new(
+            options.peer,
+            options.access_control,
+            outlets.clone(),
+            health_processor.clone(),
+            options.ip_family,
+            options.max_outlets,
+            OutletWorkerOptions {
+                sessions: None,
+                slot: None,
+                counters: None,
+                resolved_peer: None,
+                reconnect: options.reconnect,
+                keepalive: options.keepalive,
+                idle_timeout: options.idle_timeout,
+                proxy: options.proxy,
+            },
+        );
+        let builder = WorkerBuilder::with_access_control(
+            access_control,
+            Arc::new(AllowAll),
+            options.address.clone(),
+            worker,
+        );
+        if let Err(err) = builder.start(ctx).await {
+            // The listener never started, so it won't clean up after itself
+            outlets.remove(&options.address);
+            if let Some(address) = health_processor {
+                let _ = ctx.stop_processor(address).await;
+            }
+            return Err(err);
+        }
+
+        Ok(info)
+    }
+
+    /// Return the health of the Outlet at addr, if it was created with
+    /// [`OutletOptions::health_check`]
+    pub fn outlet_health(&self, addr: impl Into<Address>) -> Option<OutletHealthStatus> {
+        self.router_handle.outlets().health(&addr.into())
+    }
+
+    /// Return how many bytes went through the Outlet at addr
+    pub fn outlet_traffic(&self, addr: impl Into<Address>) -> Option<OutletTraffic> {
+        self.router_handle.outlets().traffic(&addr.into())
     }
 
     /// Create Tcp Outlet Listener at address, that connects to peer using Tcp, transforms Ockam Messages
@@ -232,6 +630,12 @@ impl TcpTransport {
     /// to Inlet using return route.
     /// Pair of corresponding Inlet and Outlet is called Portal.
     ///
+    /// A peer of the form `udp://host:port` is reached over UDP instead:
+    /// every payload received from the Inlet is sent as one datagram, and
+    /// every datagram from the peer comes back to the Inlet as one
+    /// payload. Pair it with a [framed](InletOptions::framed) Inlet so
+    /// that its client sees the same datagrams.
+    ///
     /// ```rust
     /// use ockam_transport_tcp::TcpTransport;
     /// # use ockam_node::Context;
@@ -247,12 +651,66 @@ impl TcpTransport {
         &self,
         address: impl Into<Address>,
         peer: impl Into<String>,
-    ) -> Result<()> {
+    ) -> Result<OutletInfo> {
         let options = OutletOptions::new(address.into(), peer.into(), Arc::new(AllowAll));
 
         self.create_outlet_extended(options).await
     }
 
+    /// Create a Portal relay at address, that acts as an Outlet for Inlets and forwards
+    /// everything it receives to the Outlet at outlet_route, and back. This way two
+    /// portals can be chained, e.g. to relay between networks, without a TCP hop
+    /// in the middle. A disconnection on either side tears down the other side.
+    ///
+    /// `access_control` is checked for every message coming from an Inlet,
+    /// like for [`TcpTransport::create_outlet_extended`]. If the next
+    /// Outlet doesn't answer within [`RELAY_CONNECT_TIMEOUT`], the Inlet
+    /// side is disconnected. Stop the relay and close every portal going
+    /// through it with [`TcpTransport::stop_outlet`].
+    ///
+    /// ```rust
+    /// use ockam_transport_tcp::{TcpTransport, TCP};
+    /// # use ockam_node::Context;
+    /// # use ockam_core::{AllowAll, Result, route};
+    /// # use ockam_core::compat::sync::Arc;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let outlet_route = route![(TCP, "OTHER_NETWORK_HOP:8000"), "outlet"];
+    ///
+    /// let tcp = TcpTransport::create(&ctx).await?;
+    /// tcp.create_portal_relay("relay", outlet_route, Arc::new(AllowAll)).await?;
+    /// tcp.create_inlet("127.0.0.1:5000", route!["relay"]).await?;
+    /// # tcp.stop_outlet("relay").await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn create_portal_relay(
+        &self,
+        address: impl Into<Address>,
+        outlet_route: impl Into<Route>,
+        access_control: Arc<dyn AccessControl>,
+    ) -> Result<()> {
+        let address = address.into();
+        let outlets = self.router_handle.outlets();
+        outlets.insert(address.clone(), OutletEntry::default());
+
+        let worker = PortalRelayListenWorker::new(
+            outlet_route.into(),
+            access_control.clone(),
+            outlets.clone(),
+        );
+        let builder = WorkerBuilder::with_access_control(
+            access_control,
+            Arc::new(AllowAll),
+            address.clone(),
+            worker,
+        );
+        if let Err(err) = builder.start(self.router_handle.ctx()).await {
+            outlets.remove(&address);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     // FIXME
     /*
         let worker =
@@ -307,7 +765,12 @@ impl TcpTransport {
 
      */
 
-    /// Stop outlet at addr
+    /// Stop outlet at addr and the connections it has open
+    ///
+    /// Like [`TcpTransport::shutdown_outlet`], with a timeout of
+    /// [`STOP_OUTLET_TIMEOUT`]. Once this returns, a `Ping` sent to
+    /// addr no longer opens a portal.
+    ///
     /// ```rust
     /// use ockam_transport_tcp::TcpTransport;
     /// # use ockam_node::Context;
@@ -321,7 +784,34 @@ impl TcpTransport {
     /// # Ok(()) }
     /// ```
     pub async fn stop_outlet(&self, addr: impl Into<Address>) -> Result<()> {
-        self.router_handle.stop_outlet(addr).await?;
+        self.router_handle
+            .shutdown_outlet(addr, STOP_OUTLET_TIMEOUT)
+            .await?;
         Ok(())
     }
+
+    /// Stop the outlet at addr and close every connection it has open
+    ///
+    /// The outlet stops accepting new connections at once. Each open
+    /// connection then notifies its inlet and closes. Connections still
+    /// open after `timeout` are stopped forcibly; their number is returned.
+    ///
+    /// ```rust
+    /// use core::time::Duration;
+    /// use ockam_transport_tcp::TcpTransport;
+    /// # use ockam_node::Context;
+    /// # use ockam_core::Result;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let tcp = TcpTransport::create(&ctx).await?;
+    /// tcp.create_outlet("outlet", "127.0.0.1:5000").await?;
+    /// let forced = tcp.shutdown_outlet("outlet", Duration::from_secs(5)).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn shutdown_outlet(
+        &self,
+        addr: impl Into<Address>,
+        timeout: Duration,
+    ) -> Result<usize> {
+        self.router_handle.shutdown_outlet(addr, timeout).await
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{TcpRouterHandle, TcpSendWorker};
+use crate::{ListenOptions, TcpRouterHandle, TcpSendWorker};
 use ockam_core::{
     async_trait,
     compat::{net::SocketAddr, sync::Arc},
@@ -25,11 +25,10 @@ impl TcpListenProcessor {
         ctx: &Context,
         router_handle: TcpRouterHandle,
         addr: SocketAddr,
+        options: ListenOptions,
     ) -> Result<SocketAddr> {
-        debug!("Binding TcpListener to {}", addr);
-        let inner = TcpListener::bind(addr)
-            .await
-            .map_err(TransportError::from)?;
+        debug!("Binding TcpListener to {} with {:?}", addr, options);
+        let inner = options.bind(addr).map_err(TransportError::from)?;
         let saddr = inner.local_addr().map_err(TransportError::from)?;
         let processor = Self {
             inner,
@@ -1,5 +1,6 @@
-use crate::{TcpSendWorkerMsg, TCP};
+use crate::{TcpLocalInfo, TcpSendWorkerMsg, TCP};
 use ockam_core::async_trait;
+use ockam_core::compat::net::SocketAddr;
 use ockam_core::{Address, Decodable, LocalMessage, Processor, Result, TransportMessage};
 use ockam_node::{Context, ExternalLocalInfo};
 use ockam_transport_core::TransportError;
@@ -17,15 +18,22 @@ use tracing::{error, info, trace};
 pub(crate) struct TcpRecvProcessor {
     rx: OwnedReadHalf,
     peer_addr: Address,
+    peer: SocketAddr,
     sender_internal_address: Address,
 }
 
 impl TcpRecvProcessor {
     /// Create a new `TcpRecvProcessor`
-    pub fn new(rx: OwnedReadHalf, peer_addr: Address, sender_internal_address: Address) -> Self {
+    pub fn new(
+        rx: OwnedReadHalf,
+        peer_addr: Address,
+        peer: SocketAddr,
+        sender_internal_address: Address,
+    ) -> Self {
         Self {
             rx,
             peer_addr,
+            peer,
             sender_internal_address,
         }
     }
@@ -104,9 +112,11 @@ impl Processor for TcpRecvProcessor {
 
         // Mark that message originates from some other node
         let local_info = ExternalLocalInfo::new(TCP).to_local_info()?;
+        // and which connection it arrived on
+        let tcp_info = TcpLocalInfo::new(self.peer).to_local_info()?;
 
         // Forward the message to the next hop in the route
-        ctx.forward(LocalMessage::new(msg, vec![local_info]))
+        ctx.forward(LocalMessage::new(msg, vec![local_info, tcp_info]))
             .await?;
 
         Ok(true)
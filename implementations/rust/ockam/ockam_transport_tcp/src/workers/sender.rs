@@ -220,6 +220,7 @@ impl Worker for TcpSendWorker {
         let receiver = TcpRecvProcessor::new(
             rx,
             format!("{}#{}", crate::TCP, self.peer).into(),
+            self.peer,
             self.internal_addr.clone(),
         );
 
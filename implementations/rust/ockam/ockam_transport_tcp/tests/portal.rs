@@ -1,12 +1,17 @@
 use std::time::Duration;
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
 
 use ockam_core::compat::rand::random;
-use ockam_core::{route, Result};
+use ockam_core::compat::sync::Arc;
+use ockam_core::{route, AllowAll, Any, DenyAll, Result, Routed, Worker};
 use ockam_node::Context;
-use ockam_transport_tcp::TcpTransport;
+use ockam_transport_core::FixedReconnect;
+use ockam_transport_tcp::{
+    HealthCheckOptions, InletOptions, KeepaliveOptions, OutletOptions, Socks5Proxy, TcpTransport,
+    RELAY_CONNECT_TIMEOUT,
+};
 
 const LENGTH: usize = 32;
 
@@ -101,3 +106,887 @@ async fn portal__reverse_flow__should_succeed(ctx: &mut Context) -> Result<()> {
 
     Ok(())
 }
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__validate_unreachable_outlet__should_fail(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    // Bind and immediately drop a listener to get a port nobody listens on
+    let peer = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+
+    let options =
+        OutletOptions::new("outlet".into(), peer.clone(), Arc::new(AllowAll)).validate(true);
+    assert!(tcp.create_outlet_extended(options).await.is_err());
+
+    let options = OutletOptions::new("outlet".into(), peer, Arc::new(AllowAll));
+    assert!(tcp.create_outlet_extended(options).await.is_ok());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__relay_between_portals__should_succeed(ctx: &mut Context) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    tcp.create_portal_relay("relay", route!["outlet"], Arc::new(AllowAll))
+        .await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["relay"]).await?;
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        read_assert_binary(&mut stream, payload1).await;
+        write_binary(&mut stream, payload2).await;
+    });
+
+    // Wait till listener is up
+    tokio::time::sleep(Duration::new(0, 250_000)).await;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload1).await;
+    read_assert_binary(&mut stream, payload2).await;
+
+    tokio::time::sleep(Duration::new(0, 250_000)).await;
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Never answers, like an Outlet on a node that is down
+struct Unresponsive;
+
+#[ockam_core::worker]
+impl Worker for Unresponsive {
+    type Context = Context;
+    type Message = Any;
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<Any>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 15000)]
+async fn portal__relay_without_pong__should_close_inlet(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    ctx.start_worker("unresponsive", Unresponsive).await?;
+    tcp.create_portal_relay("relay", route!["unresponsive"], Arc::new(AllowAll))
+        .await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["relay"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    let mut buf = [0u8; LENGTH];
+    let read = tokio::time::timeout(RELAY_CONNECT_TIMEOUT * 2, stream.read(&mut buf)).await;
+    assert_eq!(read.unwrap().unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__stop_relay__should_close_portals(ctx: &mut Context) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    tcp.create_portal_relay("relay", route!["outlet"], Arc::new(AllowAll))
+        .await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["relay"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload).await;
+
+    tcp.stop_outlet("relay").await?;
+
+    // Both ends of the chained portals are closed
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_health_check__should_flip_when_peer_closes(
+    ctx: &mut Context,
+) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+    let health_check = HealthCheckOptions::new(Duration::from_millis(50), 2);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer = listener.local_addr().unwrap().to_string();
+    let options =
+        OutletOptions::new("outlet".into(), peer, Arc::new(AllowAll)).health_check(health_check);
+    tcp.create_outlet_extended(options).await?;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let before = tcp.outlet_health("outlet").unwrap();
+    assert!(before.is_healthy());
+    let last_success = before.last_success().unwrap();
+
+    drop(listener);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let after = tcp.outlet_health("outlet").unwrap();
+    assert!(!after.is_healthy());
+    assert!(after.consecutive_failures() >= 2);
+    assert!(after.last_success().unwrap() >= last_success);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_health_check__should_track_peer(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+    let health_check = HealthCheckOptions::new(Duration::from_millis(50), 2);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let up = listener.local_addr().unwrap().to_string();
+    let options =
+        OutletOptions::new("up".into(), up, Arc::new(AllowAll)).health_check(health_check.clone());
+    tcp.create_outlet_extended(options).await?;
+
+    let down = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    };
+    let options =
+        OutletOptions::new("down".into(), down, Arc::new(AllowAll)).health_check(health_check);
+    tcp.create_outlet_extended(options).await?;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let up = tcp.outlet_health("up").unwrap();
+    assert!(up.is_healthy());
+    assert!(up.last_success().is_some());
+    let down = tcp.outlet_health("down").unwrap();
+    assert!(!down.is_healthy());
+    assert!(down.consecutive_failures() >= 2);
+    assert!(down.last_success().is_none());
+
+    tcp.stop_outlet("down").await?;
+    assert!(tcp.outlet_health("down").is_none());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+async fn setup_session(ctx: &Context) -> Result<(TcpTransport, TcpStream)> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        read_assert_binary(&mut stream, payload1).await;
+        write_binary(&mut stream, payload2).await;
+
+        // Keep the connection open until the outlet closes it
+        let mut buf = [0u8; LENGTH];
+        let _ = stream.read(&mut buf).await;
+    });
+
+    // Wait till listener is up
+    tokio::time::sleep(Duration::new(0, 250_000)).await;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload1).await;
+    read_assert_binary(&mut stream, payload2).await;
+
+    Ok((tcp, stream))
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__shutdown_outlet__should_close_sessions(ctx: &mut Context) -> Result<()> {
+    let (tcp, mut stream) = setup_session(ctx).await?;
+
+    let forced = tcp
+        .shutdown_outlet("outlet", Duration::from_secs(5))
+        .await?;
+    assert_eq!(forced, 0);
+
+    // The inlet side observes a clean close
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__stop_outlet__should_stop_serving(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    // An open session is closed
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    let (_peer_stream, _) = listener.accept().await.unwrap();
+    tcp.stop_outlet("outlet").await?;
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    // The `Ping` of a new session doesn't reach an outlet any more
+    let _stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    let accepted = tokio::time::timeout(Duration::from_millis(500), listener.accept()).await;
+    assert!(accepted.is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__shutdown_outlet_timeout__should_force_close(ctx: &mut Context) -> Result<()> {
+    let (tcp, _stream) = setup_session(ctx).await?;
+
+    let forced = tcp.shutdown_outlet("outlet", Duration::ZERO).await?;
+    assert_eq!(forced, 1);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 15000)]
+async fn portal__outlet_reconnect__should_survive_peer_restart(ctx: &mut Context) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap();
+    let options = OutletOptions::new(
+        "outlet".into(),
+        bind_address.to_string(),
+        Arc::new(AllowAll),
+    )
+    .reconnect(FixedReconnect::new(Duration::from_millis(100), 50));
+    tcp.create_outlet_extended(options).await?;
+
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+
+    write_binary(&mut stream, payload1).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload1).await;
+    write_binary(&mut peer, payload1).await;
+    read_assert_binary(&mut stream, payload1).await;
+
+    // The peer crashes, resetting the connection, and comes back on
+    // the same port
+    peer.set_linger(Some(Duration::ZERO)).unwrap();
+    drop(peer);
+    drop(listener);
+    let listener = TcpListener::bind(bind_address).await.unwrap();
+
+    write_binary(&mut stream, payload2).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload2).await;
+    write_binary(&mut peer, payload2).await;
+    read_assert_binary(&mut stream, payload2).await;
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 15000)]
+async fn portal__outlet_reconnect__should_propagate_clean_close(ctx: &mut Context) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap();
+    let options = OutletOptions::new(
+        "outlet".into(),
+        bind_address.to_string(),
+        Arc::new(AllowAll),
+    )
+    .reconnect(FixedReconnect::new(Duration::from_millis(100), 50));
+    tcp.create_outlet_extended(options).await?;
+
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+
+    write_binary(&mut stream, payload).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload).await;
+
+    // Like an HTTP server closing after its response: not a failure,
+    // so the close reaches the Inlet instead of triggering a reconnect
+    write_binary(&mut peer, payload).await;
+    drop(peer);
+    read_assert_binary(&mut stream, payload).await;
+
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_traffic__should_count_bytes(ctx: &mut Context) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload1).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload1).await;
+    write_binary(&mut peer, payload1).await;
+    read_assert_binary(&mut stream, payload1).await;
+    write_binary(&mut stream, payload2).await;
+    read_assert_binary(&mut peer, payload2).await;
+
+    // The outlet counts a write once it has completed
+    ctx.sleep(Duration::from_millis(100)).await;
+    let traffic = tcp.outlet_traffic("outlet").unwrap();
+    assert_eq!(traffic.bytes_to_peer(), 2 * LENGTH as u64);
+    assert_eq!(traffic.bytes_from_peer(), LENGTH as u64);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__outlet_idle_timeout__should_close_portal(ctx: &mut Context) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    let options = OutletOptions::new("outlet".into(), bind_address, Arc::new(AllowAll))
+        .idle_timeout(Duration::from_millis(200));
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload).await;
+
+    // Nothing moves anymore, both ends get closed
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// A file descriptor of this process, borrowed to inspect it
+#[cfg(target_os = "linux")]
+struct Fd(std::os::unix::io::RawFd);
+
+#[cfg(target_os = "linux")]
+impl std::os::unix::io::AsRawFd for Fd {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.0
+    }
+}
+
+/// Find the socket of this process that is bound to `addr`
+#[cfg(target_os = "linux")]
+fn own_socket(addr: std::net::SocketAddr) -> Option<Fd> {
+    std::fs::read_dir("/proc/self/fd")
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .map(Fd)
+        .find(|fd| {
+            let local = socket2::SockRef::from(fd).local_addr().ok();
+            local.and_then(|a| a.as_socket()) == Some(addr)
+        })
+}
+
+#[cfg(target_os = "linux")]
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__outlet_keepalive__should_apply_to_peer_connection(
+    ctx: &mut Context,
+) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    let keepalive = KeepaliveOptions::new(Duration::from_secs(60), Duration::from_secs(10), 3);
+    let options =
+        OutletOptions::new("outlet".into(), bind_address, Arc::new(AllowAll)).keepalive(keepalive);
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload).await;
+
+    // The outlet's end of the accepted connection
+    let fd = own_socket(peer.peer_addr().unwrap()).expect("outlet socket not found");
+    let socket = socket2::SockRef::from(&fd);
+    assert!(socket.keepalive().unwrap());
+    assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(60));
+    assert_eq!(
+        socket.keepalive_interval().unwrap(),
+        Duration::from_secs(10)
+    );
+    assert_eq!(socket.keepalive_retries().unwrap(), 3);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__max_outlets__should_refuse_extra_portals(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    let options =
+        OutletOptions::new("outlet".into(), bind_address, Arc::new(AllowAll)).max_outlets(2);
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut connections = vec![];
+    for _ in 0..2 {
+        let payload = generate_binary();
+        let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+        write_binary(&mut stream, payload).await;
+        let (mut peer, _) = listener.accept().await.unwrap();
+        read_assert_binary(&mut peer, payload).await;
+        connections.push((stream, peer));
+    }
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__max_outlets__should_hold_under_a_burst(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    let options =
+        OutletOptions::new("outlet".into(), bind_address, Arc::new(AllowAll)).max_outlets(1);
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    // Every connection makes the Inlet send a Ping, all of them reach
+    // the Outlet before the first portal is initialized
+    let (a, b, c) = tokio::join!(
+        TcpStream::connect(inlet_saddr),
+        TcpStream::connect(inlet_saddr),
+        TcpStream::connect(inlet_saddr),
+    );
+
+    let mut refused = 0;
+    for mut stream in [a.unwrap(), b.unwrap(), c.unwrap()] {
+        let mut buf = [0u8; LENGTH];
+        let read = tokio::time::timeout(Duration::from_secs(3), stream.read(&mut buf)).await;
+        if let Ok(Ok(0)) = read {
+            refused += 1;
+        }
+    }
+    assert_eq!(refused, 2);
+
+    // Only one portal connected to the peer
+    let _peer = listener.accept().await.unwrap();
+    let extra = tokio::time::timeout(Duration::from_millis(200), listener.accept()).await;
+    assert!(extra.is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_access_control__should_not_start_outlet(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    let options = OutletOptions::new("outlet".into(), bind_address, Arc::new(DenyAll));
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, generate_binary()).await;
+
+    // The Ping is refused, so the Outlet never connects to its peer
+    let accepted = tokio::time::timeout(Duration::from_millis(500), listener.accept()).await;
+    assert!(accepted.is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 10000)]
+async fn portal__stopped_outlet__should_close_cleanly(ctx: &mut Context) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet("outlet", bind_address).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload).await;
+    let (mut peer, _) = listener.accept().await.unwrap();
+    read_assert_binary(&mut peer, payload).await;
+
+    // Stop the portal worker from the outside
+    tcp.shutdown_outlet("outlet", Duration::ZERO).await?;
+
+    // The peer gets a clean EOF rather than a reset
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+
+    // and so does the client on the Inlet side
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__udp_outlet__should_tunnel_datagrams(ctx: &mut Context) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let echo = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let echo_address = echo.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        while let Ok((len, from)) = echo.recv_from(&mut buf).await {
+            let _ = echo.send_to(&buf[..len], from).await;
+        }
+    });
+
+    let options = OutletOptions::new(
+        "outlet".into(),
+        format!("udp://{}", echo_address),
+        Arc::new(AllowAll),
+    )
+    .idle_timeout(Duration::from_millis(500));
+    tcp.create_outlet_extended(options).await?;
+    let options =
+        InletOptions::new("127.0.0.1:0".into(), route!["outlet"], Arc::new(AllowAll)).framed(true);
+    let (_, inlet_saddr) = tcp.create_inlet_extended(options).await?;
+
+    // Two frames in one write still reach the peer as two datagrams
+    let mut frames = Vec::new();
+    for _ in 0..2 {
+        frames.extend_from_slice(&(LENGTH as u16).to_be_bytes());
+        frames.extend_from_slice(&payload);
+    }
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    stream.write_all(&frames).await.unwrap();
+
+    let mut echoed = vec![0u8; frames.len()];
+    stream.read_exact(&mut echoed).await.unwrap();
+    assert_eq!(echoed, frames);
+
+    let traffic = tcp.outlet_traffic("outlet").unwrap();
+    assert_eq!(traffic.bytes_to_peer(), 2 * LENGTH as u64);
+    assert_eq!(traffic.bytes_from_peer(), 2 * LENGTH as u64);
+
+    // UDP has no close, the session ends once it's idle
+    let mut buf = [0u8; LENGTH];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__udp_outlet_health_check__should_be_refused(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let options = OutletOptions::new(
+        "outlet".into(),
+        "udp://127.0.0.1:5353".into(),
+        Arc::new(AllowAll),
+    )
+    .health_check(HealthCheckOptions::new(Duration::from_millis(50), 2));
+    assert!(tcp.create_outlet_extended(options).await.is_err());
+    assert!(tcp.outlet_health("outlet").is_none());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__create_outlet__should_return_resolved_peer(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap();
+    let options = OutletOptions::new(
+        "outlet".into(),
+        bind_address.to_string(),
+        Arc::new(AllowAll),
+    )
+    .validate(true);
+    let info = tcp.create_outlet_extended(options).await?;
+
+    assert_eq!(info.address(), &"outlet".into());
+    let peer = info.peer().unwrap();
+    assert!(peer.ip().is_loopback());
+    assert_eq!(peer, bind_address);
+
+    // Otherwise the peer is only resolved once a portal opens
+    let info = tcp
+        .create_outlet("outlet2", bind_address.to_string())
+        .await?;
+    assert_eq!(info.peer(), None);
+
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet2"]).await?;
+    let _stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    let _peer = listener.accept().await.unwrap();
+    assert_eq!(info.peer(), Some(bind_address));
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__create_outlet__should_fail_on_unresolvable_peer(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let options = OutletOptions::new(
+        "outlet".into(),
+        "no-such-host.invalid:4000".into(),
+        Arc::new(AllowAll),
+    )
+    .resolve_eagerly(true);
+    assert!(tcp.create_outlet_extended(options).await.is_err());
+
+    // Without eager resolution the outlet starts and only fails per portal
+    let options = OutletOptions::new(
+        "lazy_outlet".into(),
+        "no-such-host.invalid:4000".into(),
+        Arc::new(AllowAll),
+    );
+    tcp.create_outlet_extended(options).await?;
+
+    // Nothing was started, so the address is still free
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap();
+    tcp.create_outlet("outlet", bind_address.to_string())
+        .await?;
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// A SOCKS5 server that requires `user`/`pass`, serves one connection
+/// and returns the address it was asked to connect to
+async fn socks5_server(listener: TcpListener) -> String {
+    let (mut client, _) = listener.accept().await.unwrap();
+
+    let mut greeting = [0u8; 3];
+    client.read_exact(&mut greeting).await.unwrap();
+    assert_eq!(greeting, [5, 1, 2]);
+    client.write_all(&[5, 2]).await.unwrap();
+
+    let mut auth = [0u8; 11];
+    client.read_exact(&mut auth).await.unwrap();
+    assert_eq!(&auth, b"\x01\x04user\x04pass");
+    client.write_all(&[1, 0]).await.unwrap();
+
+    // The hostname is left for the proxy to resolve
+    let mut request = [0u8; 5];
+    client.read_exact(&mut request).await.unwrap();
+    assert_eq!(request[..4], [5, 1, 0, 3]);
+    let mut host = vec![0u8; request[4] as usize];
+    client.read_exact(&mut host).await.unwrap();
+    let mut port = [0u8; 2];
+    client.read_exact(&mut port).await.unwrap();
+    let target = format!(
+        "{}:{}",
+        String::from_utf8(host).unwrap(),
+        u16::from_be_bytes(port)
+    );
+
+    let mut upstream = TcpStream::connect(target.as_str()).await.unwrap();
+    client
+        .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+        .await
+        .unwrap();
+    tokio::spawn(async move {
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+    });
+
+    target
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__socks5_proxy__should_reach_peer(ctx: &mut Context) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        read_assert_binary(&mut stream, payload1).await;
+        write_binary(&mut stream, payload2).await;
+    });
+
+    let proxy_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy =
+        Socks5Proxy::new(proxy_listener.local_addr().unwrap()).with_credentials("user", "pass");
+    let proxy_target = tokio::spawn(socks5_server(proxy_listener));
+
+    let peer = format!("localhost:{}", peer.port());
+    let options =
+        OutletOptions::new("outlet".into(), peer.clone(), Arc::new(AllowAll)).socks5_proxy(proxy);
+    tcp.create_outlet_extended(options).await?;
+    let (_, inlet_saddr) = tcp.create_inlet("127.0.0.1:0", route!["outlet"]).await?;
+
+    let mut stream = TcpStream::connect(inlet_saddr).await.unwrap();
+    write_binary(&mut stream, payload1).await;
+    read_assert_binary(&mut stream, payload2).await;
+
+    assert_eq!(proxy_target.await.unwrap(), peer);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
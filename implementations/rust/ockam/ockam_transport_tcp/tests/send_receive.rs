@@ -2,7 +2,7 @@ use ockam_core::compat::rand::{self, Rng};
 use ockam_core::{route, Address, Result, Routed, Worker};
 use ockam_node::Context;
 
-use ockam_transport_tcp::{TcpTransport, TCP};
+use ockam_transport_tcp::{ListenOptions, TcpLocalInfo, TcpTransport, TCP};
 use std::time::Duration;
 use tracing::info;
 
@@ -34,6 +34,26 @@ async fn send_receive(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+#[ockam_macros::test]
+async fn send_receive_with_listen_options(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+    let options = ListenOptions::default().backlog(16).reuse_address(true);
+    let listener_address = transport.listen_extended("127.0.0.1:0", options).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let r = route![(TCP, listener_address.to_string()), "echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
 pub struct Echoer;
 
 #[ockam_core::worker]
@@ -46,6 +66,50 @@ impl Worker for Echoer {
     }
 }
 
+pub struct PeerEchoer;
+
+#[ockam_core::worker]
+impl Worker for PeerEchoer {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        let peer = match TcpLocalInfo::find_info(msg.local_message()) {
+            Ok(info) => info.peer_address()?.to_string(),
+            Err(_) => "none".to_string(),
+        };
+        ctx.send(msg.return_route(), peer).await
+    }
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test]
+async fn tcp_local_info__should_expose_peer_address(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker("peer_echoer", PeerEchoer).await?;
+
+    let transport = TcpTransport::create(ctx).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+
+    let r = route![(TCP, listener_address.to_string()), "peer_echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "hello".to_string())
+        .await?;
+    let peer: std::net::SocketAddr = reply.parse().unwrap();
+    assert!(peer.ip().is_loopback());
+
+    // Local messages carry no TCP connection metadata
+    let reply = ctx
+        .send_and_receive::<_, _, String>(route!["peer_echoer"], "hello".to_string())
+        .await?;
+    assert_eq!(reply, "none");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
 #[allow(non_snake_case)]
 #[ockam_macros::test]
 async fn tcp_lifecycle__reconnect__should_not_error(ctx: &mut Context) -> Result<()> {